@@ -0,0 +1,227 @@
+use super::cmn::*;
+use core::iter::Iterator;
+
+/// CamCfg, shall be configured in the following manner:
+/// Level
+/// ^
+/// 0r     1f     2r               3f             4r
+/// |------+      +----------------+              +------
+/// |  ag0 |  ag1 |      ag2       |     ag3      |  ag4
+/// |      |      |                |              |
+/// +------+------+----------------+--------------+------> Ag
+/// The first angle shall be calculated from the reference, aka crank gape.
+/// The last angle shall be calculated from the last edge to 720°.
+/// "r" and "f" on event ids stand for "rising" or "falling".
+/// The configuration shown above isn't real, only example purpose.
+/// Events are listed as (angle, edge), their number is the one of the wheel.
+#[derive(Debug)]
+pub struct CamCfg {
+    pub ev: &'static [(u32, Edge)],
+    pub pol: Polarity,
+}
+
+impl CamCfg {
+    /// Cam wheel of events `ev`, checked by `check`: `CAM_CONFIGS` doesn't compile 
+    /// with an entry not closing the cycle or not alternating edges
+    pub const fn new(ev: &'static [(u32, Edge)], pol: Polarity) -> CamCfg {
+        let cfg = CamCfg { ev, pol };
+        if let Err(err) = cfg.check() {
+            panic!("{}", err.desc());
+        }
+        cfg
+    }
+
+    /// Check that the configuration describes a wheel that can be generated: 
+    /// events of non null angles closing the 720° cycle, with alternating edges. 
+    /// The last event may keep the level of the one before it, only closing the cycle.
+    pub const fn check(&self) -> Result<(), CfgErr> {
+        let ev = self.ev;
+        let ev_nr = ev.len();
+        if ev_nr < 2 {
+            return Err(CfgErr::EvNr(ev_nr));
+        }
+
+        let mut ag_sum: u32 = 0;
+        let mut idx = 0;
+        while idx < ev_nr {
+            let ag = ev[idx].0;
+            if ag == 0 {
+                return Err(CfgErr::NullAg(idx));
+            }
+            ag_sum = ag_sum.saturating_add(ag);
+            idx += 1;
+        }
+        if ag_sum != 2 * REV_DEG_TICKS {
+            return Err(CfgErr::AgSum(ag_sum));
+        }
+
+        let mut idx = 1;
+        while idx < ev_nr - 1 {
+            if ev[idx].1 as u8 == ev[idx - 1].1 as u8 {
+                return Err(CfgErr::SameEdge(idx));
+            }
+            idx += 1;
+        }
+        if ev[0].1 as u8 == ev[ev_nr - 1].1 as u8 {
+            return Err(CfgErr::SameEdge(0));
+        }
+        Ok(())
+    }
+}
+
+/// Camshaft wheel, its events being read from its configuration
+pub struct CamWheel {
+    cfg: &'static CamCfg,
+    res: AgRes,
+}
+
+impl CamWheel {
+    /// Create a camshaft wheel from a configuration of angles and edges, its 
+    /// event angles in degree ticks of `res`
+    /// 
+    /// No check is performed on the configuration, see `CamCfg::check`.
+    pub fn new(cfg: &'static CamCfg, res: AgRes) -> CamWheel {
+        CamWheel { cfg, res }
+    }
+}
+
+impl Wheel for CamWheel {
+    fn ev_nr(&self) -> usize {
+        self.cfg.ev.len()
+    }
+
+    fn ev(&self, idx: usize) -> Event {
+        let (ag, edge) = self.cfg.ev[idx];
+        Event {
+            id: idx as u16,
+            ag: self.res.from_cfg(ag),
+            edge,
+            is_gen: true,
+            pulse_us: None,
+        }
+    }
+}
+
+pub struct CamSigGen {
+    gen_pos: GenPos,
+    pol: Polarity,
+    cam: CamWheel,
+}
+
+impl CamSigGen {
+    /// Generator of the wheel of `cam`, its event angles in degree ticks of `res`
+    pub fn new(cam: &'static CamCfg, res: AgRes) -> Result<CamSigGen, CfgErr> {
+        cam.check()?;
+        let wheel = CamWheel::new(cam, res);
+        Ok(
+            CamSigGen {
+                gen_pos: GenPos::new(wheel.ev_nr()),
+                pol: cam.pol,
+                cam: wheel,
+            }
+        )
+    }
+
+    /// Output polarity, initialized from the configuration
+    pub fn polarity(&self) -> Polarity {
+        self.pol
+    }
+
+    /// Resolution of the event angles
+    pub fn res(&self) -> AgRes {
+        self.cam.res
+    }
+
+    /// Override the output polarity of the configuration
+    pub fn set_polarity(&mut self, pol: Polarity) {
+        self.pol = pol;
+    }
+
+    /// Check if the generator reached the reference (crank gap at the start of 
+    /// the 720° cycle), meaning that the next generated event is the first one
+    pub fn is_at_ref(&self) -> bool {
+        self.gen_pos.is_at_ref(&self.cam)
+    }
+
+    /// Set the direction of generation, to be used when the generator doesn't 
+    /// continue a signal generated in the other direction
+    pub fn set_direction(&mut self, dir: Direction) {
+        self.gen_pos.set_direction(dir);
+    }
+
+    /// Maximal angle the wheel can turn around from its current position
+    pub fn turn_ag_max(&self) -> u32 {
+        self.gen_pos.turn_ag_max(&self.cam)
+    }
+
+    /// Invert the direction of generation, turning around on the current position
+    pub fn turn_around(&mut self, turn_ag: u32) {
+        self.gen_pos.turn_around(turn_ag);
+    }
+
+    /// Angle between the current position and the next generated event
+    pub fn next_ag(&self) -> u32 {
+        self.gen_pos.next_ag(&self.cam)
+    }
+}
+
+impl Iterator for CamSigGen {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.gen_pos.next(&self.cam))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cam_cfg::CAM_CONFIGS;
+
+    fn cfg(ev: &'static [(u32, Edge)]) -> CamCfg {
+        CamCfg { ev, pol: Polarity::Normal }
+    }
+
+    #[test]
+    fn check_accepts_configurations() {
+        assert!(CAM_CONFIGS.iter().all(|cfg| cfg.check().is_ok()));
+        // The last event closes the cycle on the reference, keeping the level
+        assert_eq!(cfg(&[(3_600, Edge::Rising), (1_800, Edge::Falling), (1_800, Edge::Falling)]).check(), Ok(()));
+    }
+
+    #[test]
+    fn check_rejects_impossible_wheels() {
+        assert_eq!(cfg(&[(7_200, Edge::Rising)]).check(), Err(CfgErr::EvNr(1)));
+        assert_eq!(cfg(&[(3_600, Edge::Rising), (3_000, Edge::Falling)]).check(), Err(CfgErr::AgSum(6_600)));
+        assert_eq!(cfg(&[(0, Edge::Rising), (7_200, Edge::Falling)]).check(), Err(CfgErr::NullAg(0)));
+        assert_eq!(
+            cfg(&[(1_000, Edge::Rising), (1_000, Edge::Rising), (5_200, Edge::Falling)]).check(),
+            Err(CfgErr::SameEdge(1))
+        );
+        assert_eq!(cfg(&[(3_600, Edge::Rising), (3_600, Edge::Rising)]).check(), Err(CfgErr::SameEdge(0)));
+    }
+
+    #[test]
+    fn generator_follows_wheel_length() {
+        // 4 teeth of 90°, evenly spaced over the cycle
+        static CAM_4: CamCfg = CamCfg::new(
+            &[
+                (900, Edge::Rising), (900, Edge::Falling), (900, Edge::Rising), (900, Edge::Falling),
+                (900, Edge::Rising), (900, Edge::Falling), (900, Edge::Rising), (900, Edge::Falling),
+            ],
+            Polarity::Normal,
+        );
+        let mut gen = CamSigGen::new(&CAM_4, AgRes::DEG_0_1).unwrap();
+        let ev: std::vec::Vec<Event> = gen.by_ref().take(8).collect();
+        assert_eq!(ev.iter().map(|ev| ev.ag).sum::<u32>(), 2 * REV_DEG_TICKS);
+        assert!(ev.iter().enumerate().all(|(idx, ev)| ev.id as usize == idx));
+        assert!(gen.is_at_ref());
+        assert_eq!(gen.next().unwrap().id, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "event angles don't close the cycle")]
+    fn new_rejects_impossible_wheels() {
+        CamCfg::new(&[(3_600, Edge::Rising), (3_000, Edge::Falling)], Polarity::Normal);
+    }
+}
@@ -0,0 +1,74 @@
+use super::cam::CamCfg;
+use super::cmn::*;
+
+pub static CAM_CONFIGS: [CamCfg; 5] = [
+    CamCfg::new(
+        &[
+            (289,  Edge::Falling), 
+            (100,  Edge::Rising), 
+            (800,  Edge::Falling), 
+            (100,  Edge::Rising), 
+            (200,  Edge::Falling), 
+            (100,  Edge::Rising), 
+            (500,  Edge::Falling), 
+            (100,  Edge::Rising), 
+            (500,  Edge::Falling), 
+            (100,  Edge::Rising), 
+            (1100, Edge::Falling), 
+            (100,  Edge::Rising), 
+            (1100, Edge::Falling), 
+            (100,  Edge::Rising), 
+            (500,  Edge::Falling),
+            (100,  Edge::Rising), 
+            (500,  Edge::Falling), 
+            (100,  Edge::Rising), 
+            (200,  Edge::Falling), 
+            (100,  Edge::Rising), 
+            (511,  Edge::Rising),
+        ],
+        Polarity::Normal,
+    ),
+    // Single 20° tooth
+    CamCfg::new(
+        &[
+            (600,  Edge::Rising),
+            (200,  Edge::Falling),
+            (6400, Edge::Falling),
+        ],
+        Polarity::Normal,
+    ),
+    // Half moon, high over the second crank revolution
+    CamCfg::new(
+        &[
+            (3600, Edge::Rising),
+            (3600, Edge::Falling),
+        ],
+        Polarity::Normal,
+    ),
+    // Four windows of 16°, 24°, 32° and 40°, 180° apart
+    CamCfg::new(
+        &[
+            (100,  Edge::Rising),
+            (160,  Edge::Falling),
+            (1640, Edge::Rising),
+            (240,  Edge::Falling),
+            (1560, Edge::Rising),
+            (320,  Edge::Falling),
+            (1480, Edge::Rising),
+            (400,  Edge::Falling),
+            (1300, Edge::Falling),
+        ],
+        Polarity::Normal,
+    ),
+    // Two 20° teeth, one per revolution
+    CamCfg::new(
+        &[
+            (300,  Edge::Rising),
+            (200,  Edge::Falling),
+            (3400, Edge::Rising),
+            (200,  Edge::Falling),
+            (3100, Edge::Falling),
+        ],
+        Polarity::Normal,
+    ),
+];
@@ -0,0 +1,331 @@
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Edge {
+    Falling,
+    Rising,
+}
+
+impl core::ops::Not for Edge {
+    type Output = Edge;
+
+    fn not(self) -> Self::Output {
+        match self {
+            Edge::Rising => Edge::Falling,
+            Edge::Falling => Edge::Rising,
+        }
+    }
+}
+
+/// Output polarity of a generated signal
+/// * Normal: the signal idles low, rising edges are generated as low to high transitions
+/// * Inverted: the signal idles high, all edges are inverted on the output
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Polarity {
+    Normal,
+    Inverted,
+}
+
+/// Resolution of the angles of the configurations, degree ticks per revolution (0.1°)
+pub const REV_DEG_TICKS: u32 = 3_600;
+
+/// Angular resolution of the generation, degree ticks per revolution
+///
+/// It's a multiple of the resolution of the configurations, their angles converting 
+/// exactly. Finer resolutions place computed events, such as crank teeth not dividing 
+/// the revolution in whole degree ticks, closer to their exact angle.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AgRes {
+    rev_ticks: u32,
+}
+
+impl AgRes {
+    /// 0.1°, the resolution of the configurations
+    pub const DEG_0_1: AgRes = AgRes { rev_ticks: REV_DEG_TICKS };
+    /// 0.01°
+    pub const DEG_0_01: AgRes = AgRes { rev_ticks: 10 * REV_DEG_TICKS };
+    /// 0.001°, the finest resolution supported
+    pub const FINEST: AgRes = AgRes { rev_ticks: 100 * REV_DEG_TICKS };
+
+    /// Resolution of `rev_ticks` degree ticks per revolution, None if it isn't a multiple 
+    /// of the resolution of the configurations or is finer than the finest one
+    pub const fn new(rev_ticks: u32) -> Option<AgRes> {
+        if rev_ticks == 0 || !rev_ticks.is_multiple_of(REV_DEG_TICKS) || rev_ticks > AgRes::FINEST.rev_ticks {
+            None
+        } else {
+            Some(AgRes { rev_ticks })
+        }
+    }
+
+    /// Degree ticks per revolution
+    pub const fn rev_ticks(self) -> u32 {
+        self.rev_ticks
+    }
+
+    /// Convert an angle of a configuration, in degree ticks of `REV_DEG_TICKS`, to this resolution
+    pub const fn from_cfg(self, ag: u32) -> u32 {
+        ag * (self.rev_ticks / REV_DEG_TICKS)
+    }
+}
+
+/// Error found in a wheel configuration
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CfgErr {
+    /// Number of events out of the range supported by the wheel
+    EvNr(usize),
+    /// Event angles don't add up to the cycle of the wheel, total angle in degree ticks
+    AgSum(u32),
+    /// Event at the same angle as the previous one, event index
+    NullAg(usize),
+    /// Event with the same edge as the previous one, event index
+    SameEdge(usize),
+    /// No tooth, or as many missing teeth as teeth
+    ToothNr,
+    /// Teeth or gaps between them narrower than a degree tick of the resolution
+    ToothAg,
+    /// Tooth width out of the tooth pitch
+    ToothDuty,
+    /// Gap overlapping or adjoining the previous one, or out of the wheel, gap index
+    Gap(usize),
+    /// Extra tooth out of order, or not between two generated teeth, extra tooth index
+    ExtraTooth(usize),
+}
+
+impl CfgErr {
+    /// Description of the error, reported by the build of invalid configurations
+    pub const fn desc(&self) -> &'static str {
+        match self {
+            CfgErr::EvNr(_) => "number of events out of range",
+            CfgErr::AgSum(_) => "event angles don't close the cycle",
+            CfgErr::NullAg(_) => "event at the angle of the previous one",
+            CfgErr::SameEdge(_) => "event with the edge of the previous one",
+            CfgErr::ToothNr => "no tooth left",
+            CfgErr::ToothAg => "teeth narrower than the angle resolution",
+            CfgErr::ToothDuty => "tooth duty cycle out of range",
+            CfgErr::Gap(_) => "gap overlapping another one or out of the wheel",
+            CfgErr::ExtraTooth(_) => "extra tooth out of order or next to a gap",
+        }
+    }
+}
+
+/// Speeds are given in 0.01 rpm
+pub const CRPM_PER_RPM: u32 = 100;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Event {
+    pub id: u16,
+    pub ag: u32,
+    pub edge: Edge,
+    pub is_gen: bool,
+    /// When set, the event starts a pulse of this duration in µs instead of only setting a level
+    pub pulse_us: Option<u16>,
+}
+
+impl Event {
+    pub const fn new() -> Event {
+        Event {
+            id: 0,
+            ag: 0,
+            edge: Edge::Falling,
+            is_gen: true,
+            pulse_us: None,
+        }
+    }
+}
+
+impl Default for Event {
+    fn default() -> Event {
+        Event::new()
+    }
+}
+
+/// Events of a wheel, the last one being the reference
+pub trait Wheel {
+    /// Number of events of the wheel
+    fn ev_nr(&self) -> usize;
+    /// Event `idx` of the wheel, its angle being the one from the previous event
+    fn ev(&self, idx: usize) -> Event;
+}
+
+impl Wheel for [Event] {
+    fn ev_nr(&self) -> usize {
+        self.len()
+    }
+
+    fn ev(&self, idx: usize) -> Event {
+        self[idx]
+    }
+}
+
+/// Rotation direction of the generated signals
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Direction {
+    Forward,
+    Reverse,
+}
+
+/// Position of a signal generator on the events of its wheel
+///
+/// The position is the index of the last event crossed, the reference being 
+/// the last event of the wheel. Forward, events are generated as configured.
+/// Reverse, the wheel is walked backwards: each event is reached after the angle 
+/// of the event following it, and sets the level the signal had before it, 
+/// which is the edge of the event preceding it.
+#[derive(Debug, Copy, Clone)]
+pub struct GenPos {
+    pos: usize,
+    dir: Direction,
+    turn_ag: Option<u32>,
+}
+
+impl GenPos {
+    /// Create a position on the reference of a wheel with `ev_nr` events
+    pub const fn new(ev_nr: usize) -> GenPos {
+        GenPos {
+            pos: ev_nr - 1,
+            dir: Direction::Forward,
+            turn_ag: None,
+        }
+    }
+
+    /// Check if the last event crossed is the reference
+    pub fn is_at_ref<W: Wheel + ?Sized>(&self, wheel: &W) -> bool {
+        self.pos == wheel.ev_nr() - 1 && self.turn_ag.is_none()
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.dir
+    }
+
+    /// Set the direction, the next event is the one following the current 
+    /// position in the new direction
+    pub fn set_direction(&mut self, dir: Direction) {
+        self.dir = dir;
+        self.turn_ag = None;
+    }
+
+    /// Angle between the current position and the next event in the current direction, 
+    /// the maximal angle the wheel can turn around without crossing another event
+    pub fn turn_ag_max<W: Wheel + ?Sized>(&self, wheel: &W) -> u32 {
+        match self.dir {
+            Direction::Forward => wheel.ev((self.pos + 1) % wheel.ev_nr()).ag,
+            Direction::Reverse => wheel.ev(self.pos).ag,
+        }
+    }
+
+    /// Angle between the current position and the next event, the angle of the event 
+    /// returned by `next`
+    pub fn next_ag<W: Wheel + ?Sized>(&self, wheel: &W) -> u32 {
+        self.turn_ag.unwrap_or_else(|| self.turn_ag_max(wheel))
+    }
+
+    /// Invert the direction, turning around on the current position: the next event 
+    /// crosses the current one again, in the new direction, after `turn_ag`
+    pub fn turn_around(&mut self, turn_ag: u32) {
+        self.dir = match self.dir {
+            Direction::Forward => Direction::Reverse,
+            Direction::Reverse => Direction::Forward,
+        };
+        self.turn_ag = Some(turn_ag);
+    }
+
+    /// Move to the next event in the current direction and return it
+    pub fn next<W: Wheel + ?Sized>(&mut self, wheel: &W) -> Event {
+        let nr = wheel.ev_nr();
+        let prv = |idx: usize| (idx + nr - 1) % nr;
+
+        if let Some(ag) = self.turn_ag.take() {
+            let cur = wheel.ev(self.pos);
+            let edge = match self.dir {
+                Direction::Forward => cur.edge,
+                Direction::Reverse => wheel.ev(prv(self.pos)).edge,
+            };
+            return Event { ag, edge, ..cur };
+        }
+
+        match self.dir {
+            Direction::Forward => {
+                self.pos = (self.pos + 1) % nr;
+                wheel.ev(self.pos)
+            }
+            Direction::Reverse => {
+                let ag = wheel.ev(self.pos).ag;
+                self.pos = prv(self.pos);
+                Event {
+                    ag,
+                    edge: wheel.ev(prv(self.pos)).edge,
+                    ..wheel.ev(self.pos)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ev(id: u16, ag: u32, edge: Edge) -> Event {
+        Event { id, ag, edge, is_gen: true, pulse_us: None }
+    }
+
+    fn wheel() -> [Event; 4] {
+        [
+            ev(0, 100, Edge::Falling),
+            ev(1, 200, Edge::Rising),
+            ev(2, 300, Edge::Falling),
+            ev(3, 400, Edge::Rising),
+        ]
+    }
+
+    #[test]
+    fn forward_follows_wheel() {
+        let ev = &wheel()[..];
+        let mut pos = GenPos::new(ev.len());
+        assert!(pos.is_at_ref(ev));
+        for cycle in 0..2 {
+            for (idx, exp) in ev.iter().enumerate() {
+                let gen = pos.next(ev);
+                assert_eq!((gen.id, gen.ag, gen.edge), (exp.id, exp.ag, exp.edge), "cycle {}", cycle);
+                assert_eq!(pos.is_at_ref(ev), idx == ev.len() - 1);
+            }
+        }
+    }
+
+    #[test]
+    fn reverse_mirrors_wheel() {
+        let ev = &wheel()[..];
+        let mut pos = GenPos::new(ev.len());
+        pos.set_direction(Direction::Reverse);
+
+        // From the reference backwards: event 2 is 400 before, and restores the level
+        // set by event 1, and so on until the reference is crossed again
+        let exp = [(2, 400, Edge::Rising), (1, 300, Edge::Falling), (0, 200, Edge::Rising), (3, 100, Edge::Falling)];
+        for (id, ag, edge) in exp.iter() {
+            let gen = pos.next(ev);
+            assert_eq!((gen.id, gen.ag, gen.edge), (*id, *ag, *edge));
+        }
+        assert!(pos.is_at_ref(ev));
+    }
+
+    #[test]
+    fn turn_around_crosses_current_event_again() {
+        let ev = &wheel()[..];
+        let mut pos = GenPos::new(ev.len());
+        pos.next(ev);
+        pos.next(ev);
+        assert_eq!(pos.turn_ag_max(ev), 300);
+
+        pos.turn_around(150);
+        assert_eq!(pos.direction(), Direction::Reverse);
+        assert_eq!(pos.next_ag(ev), 150);
+        let gen = pos.next(ev);
+        assert_eq!((gen.id, gen.ag, gen.edge), (1, 150, Edge::Falling));
+        let gen = pos.next(ev);
+        assert_eq!((gen.id, gen.ag, gen.edge), (0, 200, Edge::Rising));
+
+        pos.turn_around(50);
+        let gen = pos.next(ev);
+        assert_eq!((gen.id, gen.ag, gen.edge), (0, 50, Edge::Falling));
+        let gen = pos.next(ev);
+        assert_eq!((gen.id, gen.ag, gen.edge), (1, 200, Edge::Rising));
+    }
+}
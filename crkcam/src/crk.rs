@@ -0,0 +1,464 @@
+use super::cmn::*;
+
+use core::iter::Iterator;
+
+/// Missing teeth of a crank wheel
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CrkGap {
+    /// First missing tooth, counted from the tooth preceding the reference gap
+    pub tooth: u16,
+    pub nr: u16,
+}
+
+impl CrkGap {
+    pub const fn new(tooth: u16, nr: u16) -> CrkGap {
+        CrkGap { tooth, nr }
+    }
+}
+
+/// Crank wheel of `tooth_nr` teeth evenly spaced, some of them missing. The reference 
+/// gap of `miss_tooth_nr` teeth follows the first tooth, other gaps and extra teeth 
+/// making irregular wheels such as 36-2-2-2 or 12+1.
+#[derive(Debug)]
+pub struct CrkCfg {
+    pub tooth_nr: u16,
+    pub miss_tooth_nr: u16,
+    /// Gaps following the reference gap, in tooth order
+    pub gaps: &'static [CrkGap],
+    /// Teeth added in the middle of the space following the given teeth, in tooth order
+    pub extra_teeth: &'static [u16],
+    /// Tooth width, from its main edge to its other edge, in percent of the tooth pitch
+    pub tooth_duty: u8,
+    pub mai_edge: Edge,
+    pub pol: Polarity,
+}
+
+impl CrkCfg {
+    /// Wheel of `tooth_nr` teeth, `miss_tooth_nr` of them missing at the reference, 
+    /// checked by `check`: an invalid entry of `CRK_CONFIGS` doesn't compile
+    pub const fn new(tooth_nr: u16, miss_tooth_nr: u16, tooth_duty: u8, mai_edge: Edge, pol: Polarity) -> CrkCfg {
+        CrkCfg {
+            tooth_nr,
+            miss_tooth_nr,
+            gaps: &[],
+            extra_teeth: &[],
+            tooth_duty,
+            mai_edge,
+            pol,
+        }
+        .checked()
+    }
+
+    /// Configuration with other gaps than the reference one, checked like `new`
+    pub const fn with_gaps(self, gaps: &'static [CrkGap]) -> CrkCfg {
+        CrkCfg { gaps, ..self }.checked()
+    }
+
+    /// Configuration with extra teeth, checked like `new`
+    pub const fn with_extra_teeth(self, extra_teeth: &'static [u16]) -> CrkCfg {
+        CrkCfg { extra_teeth, ..self }.checked()
+    }
+
+    const fn checked(self) -> CrkCfg {
+        if let Err(err) = self.check() {
+            panic!("{}", err.desc());
+        }
+        self
+    }
+
+    /// Check that the configuration describes a wheel that can be generated, 
+    /// its teeth fitting the resolution of the generation, see `check_res`
+    pub const fn check(&self) -> Result<(), CfgErr> {
+        if self.tooth_nr == 0 || self.miss_tooth_nr >= self.tooth_nr {
+            return Err(CfgErr::ToothNr);
+        }
+        if self.tooth_duty == 0 || self.tooth_duty >= 100 {
+            return Err(CfgErr::ToothDuty);
+        }
+        // Gaps are separated by a tooth at least, the first tooth always being generated
+        let mut tooth_min = if self.miss_tooth_nr == 0 { 1 } else { self.miss_tooth_nr as u32 + 2 };
+        let mut idx = 0;
+        while idx < self.gaps.len() {
+            let gap = &self.gaps[idx];
+            let end = gap.tooth as u32 + gap.nr as u32;
+            if gap.nr == 0 || (gap.tooth as u32) < tooth_min || end > self.tooth_nr as u32 {
+                return Err(CfgErr::Gap(idx));
+            }
+            tooth_min = end + 1;
+            idx += 1;
+        }
+        // Extra teeth are placed between generated edges, the last space ending on the reference
+        let mut idx = 0;
+        while idx < self.extra_teeth.len() {
+            let tooth = self.extra_teeth[idx] as usize;
+            if (idx > 0 && tooth <= self.extra_teeth[idx - 1] as usize) 
+                || tooth + 1 >= self.tooth_nr as usize
+                || !self.is_gen(2 * tooth + 1)
+                || !self.is_gen(2 * tooth + 2)
+            {
+                return Err(CfgErr::ExtraTooth(idx));
+            }
+            idx += 1;
+        }
+        Ok(())
+    }
+
+    /// Check that teeth and the gaps between them are at least a degree tick of the resolution
+    pub const fn check_res(&self, res: AgRes) -> Result<(), CfgErr> {
+        let duty_min = if self.tooth_duty < 50 { self.tooth_duty } else { 100 - self.tooth_duty };
+        if (self.tooth_nr as u64) * 100 > duty_min as u64 * res.rev_ticks() as u64 {
+            return Err(CfgErr::ToothAg);
+        }
+        // Extra teeth are half the space they're placed in, a quarter of it on each side
+        let space = 100 - self.tooth_duty as u64;
+        if !self.extra_teeth.is_empty() && (self.tooth_nr as u64) * 400 > space * res.rev_ticks() as u64 {
+            return Err(CfgErr::ToothAg);
+        }
+        Ok(())
+    }
+
+    /// Check if the event `idx` of the evenly spaced teeth is generated. Gaps extend the level 
+    /// of the main edge of the tooth preceding them up to the other edge of their last tooth.
+    const fn is_gen(&self, idx: usize) -> bool {
+        if idx >= 1 && idx <= self.miss_tooth_nr as usize * 2 {
+            return false;
+        }
+        let mut gap_idx = 0;
+        while gap_idx < self.gaps.len() {
+            let gap = &self.gaps[gap_idx];
+            let first = gap.tooth as usize * 2 - 1;
+            if idx >= first && idx < first + gap.nr as usize * 2 {
+                return false;
+            }
+            gap_idx += 1;
+        }
+        true
+    }
+}
+
+/// Output mode of the crank signal
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CrkMode {
+    /// Square signal following the teeth of the wheel
+    Tooth,
+    /// Fixed width pulse on the main edge of each tooth, as emitted by active sensors 
+    /// encoding the rotation direction in the pulse width, in µs
+    Pulse { fwd_us: u16, rev_us: u16 },
+}
+
+/// Crankshaft wheel, two events per tooth computed from its configuration
+#[derive(Debug)]
+pub struct CrkWheel {
+    pub cfg: &'static CrkCfg,
+    pub res: AgRes,
+}
+
+impl CrkWheel {
+    pub fn new(cfg: &'static CrkCfg, res: AgRes) -> CrkWheel {
+        CrkWheel { cfg, res }
+    }
+
+    /// Position of the event `idx` from the reference, in 1/400 of the tooth pitch, 
+    /// and whether it's generated
+    fn ev_pos(&self, idx: usize) -> (u64, bool) {
+        let space = 100 - self.cfg.tooth_duty as u64;
+        let mut tooth_idx = idx;
+        for (nr, &tooth) in self.cfg.extra_teeth.iter().enumerate() {
+            // Events of an extra tooth follow the end of the tooth it's placed after
+            let first = 2 * tooth as usize + 2 + 2 * nr;
+            if idx < first {
+                break;
+            }
+            if idx < first + 2 {
+                let offset = if idx == first { space } else { 3 * space };
+                return ((tooth as u64 + 1) * 400 + offset, true);
+            }
+            tooth_idx -= 2;
+        }
+        // Teeth end a whole pitch from the reference, their main edge preceding 
+        // their end by the tooth width
+        let mut pos = (tooth_idx as u64 / 2 + 1) * 400;
+        if tooth_idx.is_multiple_of(2) {
+            pos -= 4 * self.cfg.tooth_duty as u64;
+        }
+        (pos, self.cfg.is_gen(tooth_idx))
+    }
+
+    /// Angle from the reference of a position in 1/400 of the tooth pitch. Events are placed 
+    /// on the degree tick nearest to their exact angle, so that rounding errors don't 
+    /// accumulate when teeth aren't a whole number of degree ticks.
+    fn pos_ag(&self, pos: u64) -> u32 {
+        let den = self.cfg.tooth_nr as u64 * 400;
+        ((2 * pos * self.res.rev_ticks() as u64 + den) / (2 * den)) as u32
+    }
+}
+
+impl Wheel for CrkWheel {
+    fn ev_nr(&self) -> usize {
+        (self.cfg.tooth_nr as usize + self.cfg.extra_teeth.len()) * 2
+    }
+
+    fn ev(&self, idx: usize) -> Event {
+        let (pos, is_gen) = self.ev_pos(idx);
+        let prev_ag = if idx == 0 { 0 } else { self.pos_ag(self.ev_pos(idx - 1).0) };
+        // Extra teeth keep main edges on even events
+        let edge = if idx.is_multiple_of(2) {
+            self.cfg.mai_edge
+        } else {
+            !self.cfg.mai_edge
+        };
+        Event {
+            id: idx as u16,
+            ag: self.pos_ag(pos) - prev_ag,
+            edge,
+            is_gen,
+            pulse_us: None,
+        }
+    }
+}
+
+pub struct CrkSigGen {
+    gen_pos: GenPos,
+    pol: Polarity,
+    mode: CrkMode,
+    crk: CrkWheel,
+}
+
+impl CrkSigGen {
+    /// Generator of the wheel of `cfg`, its event angles in degree ticks of `res`
+    pub fn new(cfg: &'static CrkCfg, res: AgRes) -> Result<CrkSigGen, CfgErr> {
+        cfg.check()?;
+        cfg.check_res(res)?;
+        let crk = CrkWheel::new(cfg, res);
+        Ok(CrkSigGen {
+            gen_pos: GenPos::new(crk.ev_nr()),
+            pol: cfg.pol,
+            mode: CrkMode::Tooth,
+            crk,
+        })
+    }
+
+    /// Output polarity, initialized from the configuration
+    pub fn polarity(&self) -> Polarity {
+        self.pol
+    }
+
+    /// Resolution of the event angles
+    pub fn res(&self) -> AgRes {
+        self.crk.res
+    }
+
+    /// Override the output polarity of the configuration
+    pub fn set_polarity(&mut self, pol: Polarity) {
+        self.pol = pol;
+    }
+
+    pub fn mode(&self) -> CrkMode {
+        self.mode
+    }
+
+    /// Set the output mode, taken into account from the next generated event
+    pub fn set_mode(&mut self, mode: CrkMode) {
+        self.mode = mode;
+    }
+
+    /// Check if the generator reached the reference (crank gap), meaning that 
+    /// the next generated event is the first one of the revolution
+    pub fn is_at_ref(&self) -> bool {
+        self.gen_pos.is_at_ref(&self.crk)
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.gen_pos.direction()
+    }
+
+    /// Set the direction of generation, to be used when the generator doesn't 
+    /// continue a signal generated in the other direction
+    pub fn set_direction(&mut self, dir: Direction) {
+        self.gen_pos.set_direction(dir);
+    }
+
+    /// Angle between the current position and the next event
+    pub fn next_ag(&self) -> u32 {
+        self.gen_pos.next_ag(&self.crk)
+    }
+
+    /// Maximal angle the wheel can turn around from its current position
+    pub fn turn_ag_max(&self) -> u32 {
+        self.gen_pos.turn_ag_max(&self.crk)
+    }
+
+    /// Invert the direction of generation, turning around on the current position
+    pub fn turn_around(&mut self, turn_ag: u32) {
+        self.gen_pos.turn_around(turn_ag);
+    }
+}
+
+impl Iterator for CrkSigGen {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ev = self.gen_pos.next(&self.crk);
+        match self.mode {
+            CrkMode::Tooth => Some(ev),
+            CrkMode::Pulse { fwd_us, rev_us } => {
+                // Main edges of the teeth are the even events of the wheel
+                let is_tooth = ev.is_gen && ev.id.is_multiple_of(2);
+                let width = match self.gen_pos.direction() {
+                    Direction::Forward => fwd_us,
+                    Direction::Reverse => rev_us,
+                };
+                Some(Event {
+                    edge: Edge::Rising,
+                    is_gen: is_tooth,
+                    pulse_us: if is_tooth { Some(width) } else { None },
+                    ..ev
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crk_cfg::CRK_CONFIGS;
+
+    #[test]
+    fn wheel_has_missing_teeth() {
+        let crk = CrkWheel::new(&CRK_CONFIGS[2], AgRes::DEG_0_1);
+        assert_eq!(crk.ev_nr(), 120);
+        let ev: std::vec::Vec<Event> = (0..crk.ev_nr()).map(|idx| crk.ev(idx)).collect();
+        assert_eq!(ev.iter().map(|ev| ev.ag).sum::<u32>(), REV_DEG_TICKS);
+        for (idx, ev) in ev.iter().enumerate() {
+            assert_eq!(ev.is_gen, !(1..=4).contains(&idx), "event {}", idx);
+            let edge = if idx.is_multiple_of(2) { Edge::Falling } else { Edge::Rising };
+            assert_eq!(ev.edge, edge, "event {}", idx);
+        }
+    }
+
+    #[test]
+    fn check_rejects_impossible_wheels() {
+        assert!(crate::crk_cfg::CRK_CONFIGS.iter().all(|cfg| cfg.check().is_ok()));
+        let cfg = |tooth_nr, miss_tooth_nr| CrkCfg { tooth_nr, miss_tooth_nr, ..CRK_CONFIGS[2] };
+        assert_eq!(cfg(0, 0).check(), Err(CfgErr::ToothNr));
+        assert_eq!(cfg(36, 36).check(), Err(CfgErr::ToothNr));
+        assert_eq!(cfg(2_000, 1).check_res(AgRes::DEG_0_01), Ok(()));
+        assert_eq!(cfg(2_000, 1).check_res(AgRes::DEG_0_1), Err(CfgErr::ToothAg));
+        assert_eq!(CrkCfg { tooth_duty: 0, ..CRK_CONFIGS[2] }.check(), Err(CfgErr::ToothDuty));
+        assert_eq!(CrkCfg { tooth_duty: 100, ..CRK_CONFIGS[2] }.check(), Err(CfgErr::ToothDuty));
+        // Gaps of 0.06° between teeth
+        assert_eq!(CrkCfg { tooth_duty: 99, ..CRK_CONFIGS[2] }.check_res(AgRes::DEG_0_1), Err(CfgErr::ToothAg));
+        assert_eq!(CrkCfg { tooth_duty: 99, ..CRK_CONFIGS[2] }.check_res(AgRes::DEG_0_01), Ok(()));
+    }
+
+    #[test]
+    fn check_rejects_impossible_gaps() {
+        let gaps = |gaps| CrkCfg { gaps, ..CRK_CONFIGS[2] };
+        assert_eq!(gaps(&[CrkGap { tooth: 4, nr: 2 }, CrkGap { tooth: 58, nr: 2 }]).check(), Ok(()));
+        // Adjoining the reference gap, overlapping, out of the wheel
+        assert_eq!(gaps(&[CrkGap { tooth: 3, nr: 1 }]).check(), Err(CfgErr::Gap(0)));
+        assert_eq!(gaps(&[CrkGap { tooth: 10, nr: 2 }, CrkGap { tooth: 11, nr: 2 }]).check(), Err(CfgErr::Gap(1)));
+        assert_eq!(gaps(&[CrkGap { tooth: 59, nr: 2 }]).check(), Err(CfgErr::Gap(0)));
+        assert_eq!(gaps(&[CrkGap { tooth: 10, nr: 0 }]).check(), Err(CfgErr::Gap(0)));
+
+        let extra = |extra_teeth| CrkCfg { extra_teeth, ..CRK_CONFIGS[2] };
+        assert_eq!(extra(&[3, 10]).check(), Ok(()));
+        assert_eq!(extra(&[10, 3]).check(), Err(CfgErr::ExtraTooth(1)));
+        assert_eq!(extra(&[0]).check(), Err(CfgErr::ExtraTooth(0)));
+        assert_eq!(extra(&[59]).check(), Err(CfgErr::ExtraTooth(0)));
+        // Extra teeth 0.075° away from the teeth around them
+        let cfg = CrkCfg { tooth_nr: 600, ..extra(&[3]) };
+        assert_eq!(cfg.check_res(AgRes::DEG_0_1), Err(CfgErr::ToothAg));
+        assert_eq!(cfg.check_res(AgRes::DEG_0_01), Ok(()));
+    }
+
+    #[test]
+    fn wheel_has_several_gaps() {
+        static CFG_36_2_2_2: CrkCfg = CrkCfg::new(36, 2, 50, Edge::Falling, Polarity::Normal)
+            .with_gaps(&[CrkGap::new(13, 2), CrkGap::new(19, 2)]);
+        let crk = CrkWheel::new(&CFG_36_2_2_2, AgRes::DEG_0_1);
+        assert_eq!(crk.ev_nr(), 72);
+        assert_eq!((0..crk.ev_nr()).map(|idx| crk.ev(idx).ag).sum::<u32>(), REV_DEG_TICKS);
+        let skipped: std::vec::Vec<usize> = (0..crk.ev_nr()).filter(|&idx| !crk.ev(idx).is_gen).collect();
+        assert_eq!(skipped, [1, 2, 3, 4, 25, 26, 27, 28, 37, 38, 39, 40]);
+    }
+
+    #[test]
+    fn extra_tooth_splits_a_space() {
+        static CFG_12_1: CrkCfg = CrkCfg::new(12, 0, 50, Edge::Falling, Polarity::Normal)
+            .with_extra_teeth(&[0]);
+        let crk = CrkWheel::new(&CFG_12_1, AgRes::DEG_0_1);
+        assert_eq!(crk.ev_nr(), 26);
+        // Tooth 0 from 15° to 30°, extra tooth from 33.75° to 41.25°, tooth 1 from 45°
+        let ag: std::vec::Vec<u32> = (0..5).map(|idx| crk.ev(idx).ag).collect();
+        assert_eq!(ag, [150, 150, 38, 75, 37]);
+        assert_eq!(crk.ev(2).edge, Edge::Falling);
+        assert!((0..crk.ev_nr()).all(|idx| crk.ev(idx).is_gen));
+        assert_eq!((0..crk.ev_nr()).map(|idx| crk.ev(idx).ag).sum::<u32>(), REV_DEG_TICKS);
+        assert_eq!(crk.ev(25).id, 25);
+    }
+
+    #[test]
+    fn tooth_duty_sets_tooth_width() {
+        static CFG_60_2_25: CrkCfg = CrkCfg::new(60, 2, 25, Edge::Falling, Polarity::Normal);
+        let crk = CrkWheel::new(&CFG_60_2_25, AgRes::DEG_0_1);
+        // Pitch of 6°, main edges 1.5° before the end of their tooth
+        for idx in 0..crk.ev_nr() {
+            let ag = if idx % 2 == 0 { 45 } else { 15 };
+            assert_eq!(crk.ev(idx).ag, ag, "event {}", idx);
+        }
+    }
+
+    #[test]
+    fn encoder_wheel_spreads_rounding() {
+        // Half teeth of 0.25°, placed on the nearest degree tick
+        static CFG_720: CrkCfg = CrkCfg::new(720, 1, 50, Edge::Falling, Polarity::Normal);
+        let crk = CrkWheel::new(&CFG_720, AgRes::DEG_0_1);
+        assert_eq!(crk.ev_nr(), 1_440);
+        let mut ag = 0;
+        for idx in 0..crk.ev_nr() {
+            let ev = crk.ev(idx);
+            assert!(ev.ag == 2 || ev.ag == 3, "event {}", idx);
+            ag += ev.ag;
+            // Within half a degree tick of the exact angle, in quarters of degree ticks
+            assert!((ag as i64 * 4 - (idx as i64 + 1) * 10).abs() <= 2, "event {}", idx);
+        }
+        assert_eq!(ag, REV_DEG_TICKS);
+        assert_eq!(crk.ev(1_439).id, 1_439);
+
+        // Exact at 0.01°
+        let crk = CrkWheel::new(&CFG_720, AgRes::DEG_0_01);
+        assert!((0..crk.ev_nr()).all(|idx| crk.ev(idx).ag == 25));
+    }
+
+    #[test]
+    #[should_panic(expected = "no tooth left")]
+    fn new_rejects_impossible_wheels() {
+        CrkCfg::new(36, 36, 50, Edge::Falling, Polarity::Normal);
+    }
+
+    #[test]
+    fn generator_wraps_on_reference() {
+        let mut gen = CrkSigGen::new(&CRK_CONFIGS[2], AgRes::DEG_0_1).unwrap();
+        assert!(gen.is_at_ref());
+        let ag: u32 = gen.by_ref().take(120).map(|ev| ev.ag).sum();
+        assert_eq!(ag, REV_DEG_TICKS);
+        assert!(gen.is_at_ref());
+        assert_eq!(gen.next().unwrap().id, 0);
+    }
+
+    #[test]
+    fn pulse_mode_encodes_direction() {
+        let mut gen = CrkSigGen::new(&CRK_CONFIGS[2], AgRes::DEG_0_1).unwrap();
+        gen.set_mode(CrkMode::Pulse { fwd_us: 45, rev_us: 90 });
+
+        let pulses: std::vec::Vec<Event> = gen.by_ref().take(120).filter(|ev| ev.is_gen).collect();
+        assert_eq!(pulses.len(), 58);
+        assert!(pulses.iter().all(|ev| ev.pulse_us == Some(45) && ev.edge == Edge::Rising));
+
+        gen.set_direction(Direction::Reverse);
+        let pulses = gen.take(120).filter(|ev| ev.is_gen).count();
+        assert_eq!(pulses, 58);
+    }
+}
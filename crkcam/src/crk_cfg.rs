@@ -0,0 +1,143 @@
+use super::cmn::*;
+use super::crk::{CrkCfg, CrkGap};
+
+pub static CRK_CONFIGS: [CrkCfg; 18] = [
+    CrkCfg::new(
+        120, 
+        2, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ),
+    CrkCfg::new(
+        120, 
+        1, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ),
+    CrkCfg::new(
+        60, 
+        2, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ),
+    CrkCfg::new(
+        60, 
+        1, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ),
+    CrkCfg::new(
+        30, 
+        2, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ),
+    CrkCfg::new(
+        30, 
+        1, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ),
+    // 36-2-2-2, gaps of 2 teeth separated by 10, 4 and 16 teeth
+    CrkCfg::new(
+        36, 
+        2, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ).with_gaps(&[CrkGap::new(13, 2), CrkGap::new(19, 2)]),
+    // 36-1-1, gaps half a revolution apart
+    CrkCfg::new(
+        36, 
+        1, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ).with_gaps(&[CrkGap::new(19, 1)]),
+    // 12+1
+    CrkCfg::new(
+        12, 
+        0, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ).with_extra_teeth(&[0]),
+    // 24+1
+    CrkCfg::new(
+        24, 
+        0, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ).with_extra_teeth(&[0]),
+    // 36-1
+    CrkCfg::new(
+        36, 
+        1, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ),
+    // 6+1
+    CrkCfg::new(
+        6, 
+        0, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ).with_extra_teeth(&[0]),
+    // 4+1
+    CrkCfg::new(
+        4, 
+        0, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ).with_extra_teeth(&[0]),
+    // 3+1
+    CrkCfg::new(
+        3, 
+        0, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ).with_extra_teeth(&[0]),
+    // 24
+    CrkCfg::new(
+        24, 
+        0, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ),
+    // 180 teeth, slots of Nissan optical sensors turning at the camshaft speed
+    CrkCfg::new(
+        180, 
+        0, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ),
+    // 32-2
+    CrkCfg::new(
+        32, 
+        2, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    ),
+    // 36-2
+    CrkCfg::new(
+        36, 
+        2, 
+        50,
+        Edge::Falling,
+        Polarity::Normal
+    )
+];
@@ -0,0 +1,24 @@
+//! Crank and cam signal model: wheel configurations and the generators 
+//! producing their events, independent of the hardware generating them.
+//!
+//! The `std` feature adds the host simulation of the generation, its
+//! waveform export and timing diagrams of the configurations.
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+
+pub mod cam;
+pub mod cam_cfg;
+pub mod cmn;
+pub mod crk;
+pub mod crk_cfg;
+pub mod pattern;
+pub mod pattern_cfg;
+pub mod profile;
+pub mod profile_cfg;
+#[cfg(any(test, feature = "std"))]
+pub mod puml;
+pub mod sched;
+pub mod siggen;
+#[cfg(any(test, feature = "std"))]
+pub mod sim;
+#[cfg(any(test, feature = "std"))]
+pub mod vcd;
@@ -0,0 +1,64 @@
+use super::cam_cfg::CAM_CONFIGS;
+use super::crk_cfg::CRK_CONFIGS;
+use super::pattern_cfg::PATTERNS;
+
+/// Trigger pattern of an engine, named pair of crank and cam configurations
+#[derive(Debug)]
+pub struct Pattern {
+    pub name: &'static str,
+    /// Index in `CRK_CONFIGS`
+    pub crk_id: u8,
+    /// Index in `CAM_CONFIGS`
+    pub cam_id: u8,
+}
+
+impl Pattern {
+    /// Maximal length of the names, in bytes
+    pub const NAME_LEN_MAX: usize = 16;
+
+    /// Pattern `name` of `CRK_CONFIGS[crk_id]` and `CAM_CONFIGS[cam_id]`. `PATTERNS` 
+    /// doesn't compile with a name longer than `NAME_LEN_MAX` or an index out of range.
+    pub const fn new(name: &'static str, crk_id: u8, cam_id: u8) -> Pattern {
+        if name.is_empty() || name.len() > Pattern::NAME_LEN_MAX {
+            panic!("pattern name length out of range");
+        }
+        if crk_id as usize >= CRK_CONFIGS.len() || cam_id as usize >= CAM_CONFIGS.len() {
+            panic!("pattern configuration out of range");
+        }
+        Pattern { name, crk_id, cam_id }
+    }
+}
+
+/// Index in `PATTERNS` of the pattern named `name`, trailing null bytes being ignored
+pub fn find(name: &[u8]) -> Option<usize> {
+    let len = name.iter().rposition(|&b| b != 0).map_or(0, |pos| pos + 1);
+    PATTERNS.iter().position(|pat| pat.name.as_bytes() == &name[..len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cam::CamSigGen;
+    use crate::cmn::AgRes;
+    use crate::crk::CrkSigGen;
+
+    #[test]
+    fn patterns_can_be_generated() {
+        for (idx, pat) in PATTERNS.iter().enumerate() {
+            let crk = &CRK_CONFIGS[pat.crk_id as usize];
+            let cam = &CAM_CONFIGS[pat.cam_id as usize];
+            assert!(CrkSigGen::new(crk, AgRes::DEG_0_1).is_ok(), "{}", pat.name);
+            assert!(CamSigGen::new(cam, AgRes::DEG_0_1).is_ok(), "{}", pat.name);
+            assert_eq!(find(pat.name.as_bytes()), Some(idx), "{}", pat.name);
+        }
+    }
+
+    #[test]
+    fn find_ignores_padding() {
+        assert_eq!(find(b"36-1\0\0\0\0\0\0\0\0\0\0\0\0"), find(b"36-1"));
+        assert!(find(b"36-1").is_some());
+        assert_eq!(find(b"36-1\0x"), None);
+        assert_eq!(find(b""), None);
+        assert_eq!(find(b"36"), None);
+    }
+}
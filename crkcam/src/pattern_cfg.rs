@@ -0,0 +1,22 @@
+use super::pattern::Pattern;
+
+/// Trigger patterns found on production engines, some of them approximated by the 
+/// wheels the generators support
+pub static PATTERNS: [Pattern; 12] = [
+    Pattern::new("36-1", 10, 1),
+    Pattern::new("60-2", 2, 1),
+    Pattern::new("36-2-2-2", 6, 1),
+    Pattern::new("36-1-1", 7, 1),
+    Pattern::new("12+1", 8, 1),
+    Pattern::new("24+1", 9, 1),
+    Pattern::new("6+1", 11, 1),
+    Pattern::new("4+1", 12, 1),
+    Pattern::new("3+1", 13, 1),
+    // 360 slots and 4 windows on a disc turning at the camshaft speed
+    Pattern::new("nissan-360", 15, 3),
+    // Crank wheel only, the engine has no cam sensor and finds its phase from the manifold 
+    // pressure: the single tooth cam is a reference the ECU ignores, not the odd-fire phasing
+    Pattern::new("harley-32-2", 16, 1),
+    // Sensors of the eccentric shaft, the cam output signaling each revolution
+    Pattern::new("mazda-rotary", 17, 4),
+];
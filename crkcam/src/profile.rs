@@ -0,0 +1,160 @@
+/// Point of a speed profile
+#[derive(Debug, Copy, Clone)]
+pub struct ProfilePt {
+    /// Time from the start of the profile, ms
+    pub t_ms: u32,
+    /// Speed at that time, 0.01 rpm
+    pub crpm: u32,
+}
+
+impl ProfilePt {
+    pub const fn new(t_ms: u32, rpm: u32) -> ProfilePt {
+        ProfilePt {
+            t_ms,
+            crpm: rpm * super::cmn::CRPM_PER_RPM,
+        }
+    }
+}
+
+/// Piecewise-linear speed versus time profile, points shall be sorted by time
+/// and the first one shall be at 0 ms.
+#[derive(Debug)]
+pub struct Profile {
+    pub pts: &'static [ProfilePt],
+}
+
+impl Profile {
+    /// Duration of the profile, ms
+    pub fn duration_ms(&self) -> u32 {
+        self.pts.last().map_or(0, |pt| pt.t_ms)
+    }
+
+    /// Speed at a time of the profile, interpolated between its points
+    ///
+    /// **Arguments**
+    ///
+    /// * t: time from the start of the profile
+    /// * clk: number of time units per second
+    pub fn speed_at(&self, t: u64, clk: u64) -> u32 {
+        let to_clk = |t_ms: u32| t_ms as u64 * clk / 1_000;
+        for seg in self.pts.windows(2) {
+            let (t0, t1) = (to_clk(seg[0].t_ms), to_clk(seg[1].t_ms));
+            if t < t1 {
+                if t1 == t0 {
+                    return seg[1].crpm;
+                }
+                let ds = seg[1].crpm as i64 - seg[0].crpm as i64;
+                let dt = (t.max(t0) - t0) as i64;
+                return (seg[0].crpm as i64 + ds * dt / (t1 - t0) as i64) as u32;
+            }
+        }
+        self.pts.last().map_or(0, |pt| pt.crpm)
+    }
+}
+
+/// Playback state of a profile
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PlayerSts {
+    Playing,
+    Paused,
+    Ended,
+}
+
+/// Profile player, providing the speed to generate as time goes
+pub struct ProfilePlayer {
+    prof: &'static Profile,
+    /// Time from the start of the profile, in clock units
+    t: u64,
+    /// Number of time units per second
+    clk: u64,
+    looped: bool,
+    sts: PlayerSts,
+}
+
+impl ProfilePlayer {
+    /// Create a player starting the profile from its beginning
+    ///
+    /// **Arguments**
+    ///
+    /// * prof: profile to play
+    /// * looped: restart the profile from its beginning when its end is reached
+    /// * clk: number of time units per second used to advance the player
+    pub fn new(prof: &'static Profile, looped: bool, clk: u64) -> ProfilePlayer {
+        ProfilePlayer {
+            prof,
+            t: 0,
+            clk,
+            looped,
+            sts: PlayerSts::Playing,
+        }
+    }
+
+    /// Pause or resume the playback, an ended profile cannot be resumed
+    pub fn set_paused(&mut self, paused: bool) {
+        self.sts = match (self.sts, paused) {
+            (PlayerSts::Ended, _) => PlayerSts::Ended,
+            (_, true) => PlayerSts::Paused,
+            (_, false) => PlayerSts::Playing,
+        };
+    }
+
+    /// Speed at the current time of the profile, 0.01 rpm
+    pub fn speed(&self) -> u32 {
+        self.prof.speed_at(self.t, self.clk)
+    }
+
+    /// Advance the profile by `dt` time units
+    ///
+    /// **Return value**
+    ///
+    /// * Some(u32): speed to generate, 0.01 rpm
+    /// * None: the profile is paused or ended, speed shall be kept
+    pub fn advance(&mut self, dt: u64) -> Option<u32> {
+        if self.sts != PlayerSts::Playing {
+            return None;
+        }
+
+        let end = self.prof.duration_ms() as u64 * self.clk / 1_000;
+        self.t += dt;
+        if self.t >= end {
+            if self.looped && end > 0 {
+                self.t %= end;
+            } else {
+                self.t = end;
+                self.sts = PlayerSts::Ended;
+            }
+        }
+        Some(self.speed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static PROF: Profile = Profile {
+        pts: &[ProfilePt::new(0, 1_000), ProfilePt::new(1_000, 2_000), ProfilePt::new(2_000, 2_000)],
+    };
+
+    #[test]
+    fn speed_is_interpolated() {
+        assert_eq!(PROF.speed_at(0, 1_000), 100_000);
+        assert_eq!(PROF.speed_at(250, 1_000), 125_000);
+        assert_eq!(PROF.speed_at(1_500, 1_000), 200_000);
+        assert_eq!(PROF.speed_at(5_000, 1_000), 200_000);
+    }
+
+    #[test]
+    fn player_ends_or_loops() {
+        let mut player = ProfilePlayer::new(&PROF, false, 1_000);
+        assert_eq!(player.advance(500), Some(150_000));
+        player.set_paused(true);
+        assert_eq!(player.advance(500), None);
+        player.set_paused(false);
+        assert_eq!(player.advance(2_000), Some(200_000));
+        assert_eq!(player.advance(1), None);
+
+        let mut player = ProfilePlayer::new(&PROF, true, 1_000);
+        assert_eq!(player.advance(2_500), Some(150_000));
+    }
+}
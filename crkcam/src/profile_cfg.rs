@@ -0,0 +1,34 @@
+use super::profile::{Profile, ProfilePt};
+
+pub static PROFILES: [Profile; 3] = [
+    // Cranking, start and idle
+    Profile {
+        pts: &[
+            ProfilePt::new(0, 200),
+            ProfilePt::new(1_500, 250),
+            ProfilePt::new(2_000, 1_200),
+            ProfilePt::new(4_000, 800),
+            ProfilePt::new(10_000, 800),
+        ],
+    },
+    // Revving from idle, then deceleration with fuel cut back to idle
+    Profile {
+        pts: &[
+            ProfilePt::new(0, 800),
+            ProfilePt::new(2_000, 800),
+            ProfilePt::new(4_000, 6_000),
+            ProfilePt::new(5_000, 6_000),
+            ProfilePt::new(9_000, 1_500),
+            ProfilePt::new(10_000, 800),
+            ProfilePt::new(12_000, 800),
+        ],
+    },
+    // Full range sweep
+    Profile {
+        pts: &[
+            ProfilePt::new(0, 20),
+            ProfilePt::new(30_000, 12_000),
+            ProfilePt::new(60_000, 20),
+        ],
+    },
+];
@@ -0,0 +1,152 @@
+//! PlantUML binary timing diagrams of the generated signals, in degrees over
+//! the 720° engine cycle.
+use super::cam::CamSigGen;
+use super::cmn::{AgRes, Edge, Event, Polarity};
+use super::crk::CrkSigGen;
+
+use std::io::{Result, Write};
+use std::vec::Vec;
+
+/// Width of 100° in the diagram, pixels, widened to show the shortest levels
+const SCALE_PX: u32 = 150;
+/// Minimal width of a level in the diagram, pixels
+const LEVEL_MIN_PX: u32 = 10;
+
+/// Signal of the diagram
+struct Signal {
+    /// PlantUML alias
+    alias: &'static str,
+    /// Level at the start of the cycle, true if high
+    init: bool,
+    /// Angles of the level changes over the cycle, from the reference, and levels after them
+    changes: Vec<(u32, bool)>,
+}
+
+impl Signal {
+    /// Level changes of the output generated from `ev` over one cycle of `cycle_ag` degree ticks
+    fn new(alias: &'static str, ev: impl Iterator<Item = Event>, pol: Polarity, cycle_ag: u32) -> Signal {
+        let inverted = pol == Polarity::Inverted;
+        let mut ag = 0;
+        let mut levels = Vec::new();
+        for ev in ev {
+            ag += ev.ag;
+            if ev.is_gen {
+                levels.push((ag, (ev.edge == Edge::Rising) != inverted));
+            }
+            if ag >= cycle_ag {
+                break;
+            }
+        }
+
+        // The cycle starts at the level set by its last event
+        let init = levels.last().map_or(inverted, |&(_, level)| level);
+        let mut changes: Vec<(u32, bool)> = Vec::new();
+        for (ag, level) in levels {
+            if level != changes.last().map_or(init, |&(_, last)| last) {
+                changes.push((ag, level));
+            }
+        }
+        Signal { alias, init, changes }
+    }
+
+    /// Shortest level of the signal, degree ticks, None if it doesn't change
+    fn min_level_ag(&self) -> Option<u32> {
+        self.changes.windows(2).map(|w| w[1].0 - w[0].0).min()
+    }
+}
+
+/// Angle in degrees
+fn deg(ag: u32, res: AgRes) -> f64 {
+    ag as f64 * 360.0 / res.rev_ticks() as f64
+}
+
+fn level_name(level: bool) -> &'static str {
+    if level {
+        "high"
+    } else {
+        "low"
+    }
+}
+
+/// Write a PlantUML timing diagram of one cycle of the crank and/or the cam,
+/// from the reference, with their current polarity
+pub fn write_puml<W: Write>(w: &mut W, crk: Option<CrkSigGen>, cam: Option<CamSigGen>) -> Result<()> {
+    let res = match (crk.as_ref(), cam.as_ref()) {
+        (Some(crk), _) => crk.res(),
+        (None, Some(cam)) => cam.res(),
+        (None, None) => AgRes::DEG_0_1,
+    };
+    let cycle_ag = 2 * res.rev_ticks();
+    let mut sigs = Vec::new();
+    writeln!(w, "@startuml")?;
+    writeln!(w)?;
+    if let Some(crk) = crk {
+        writeln!(w, "binary \"Crank\" as CRK")?;
+        let pol = crk.polarity();
+        sigs.push(Signal::new("CRK", crk, pol, cycle_ag));
+    }
+    if let Some(cam) = cam {
+        writeln!(w, "binary \"Cam\" as CAM")?;
+        let pol = cam.polarity();
+        sigs.push(Signal::new("CAM", cam, pol, cycle_ag));
+    }
+    let min_ag = sigs.iter().filter_map(Signal::min_level_ag).min().unwrap_or(cycle_ag);
+    let px = SCALE_PX.max(LEVEL_MIN_PX * 100 * (res.rev_ticks() / 360) / min_ag.max(1));
+    writeln!(w, "scale 100 as {} pixels", px)?;
+    writeln!(w)?;
+
+    writeln!(w, "@0")?;
+    for sig in sigs.iter() {
+        writeln!(w, "{} is {}", sig.alias, level_name(sig.init))?;
+    }
+
+    // Changes of all signals, in angle order
+    let mut changes: Vec<(u32, &str, bool)> = sigs
+        .iter()
+        .flat_map(|sig| sig.changes.iter().map(move |&(ag, level)| (ag, sig.alias, level)))
+        .collect();
+    changes.sort_by_key(|&(ag, _, _)| ag);
+    let mut last_ag = 0;
+    for (ag, alias, level) in changes {
+        if ag != last_ag {
+            writeln!(w, "@{}", deg(ag, res))?;
+            last_ag = ag;
+        }
+        writeln!(w, "{} is {}", alias, level_name(level))?;
+    }
+
+    writeln!(w)?;
+    writeln!(w, "@enduml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cam_cfg::CAM_CONFIGS;
+    use crate::crk_cfg::CRK_CONFIGS;
+
+    fn puml(crk: Option<CrkSigGen>, cam: Option<CamSigGen>) -> String {
+        let mut out = std::vec::Vec::new();
+        write_puml(&mut out, crk, cam).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn cam_starts_at_last_level() {
+        let out = puml(None, Some(CamSigGen::new(&CAM_CONFIGS[0], AgRes::DEG_0_01).unwrap()));
+        assert!(out.contains("@0\nCAM is high\n@28.9\nCAM is low\n@38.9\nCAM is high\n"));
+        // The last event, on the reference, keeps the level high
+        assert!(out.contains("@658.9\nCAM is low\n@668.9\nCAM is high\n\n@enduml"));
+        assert!(!out.contains("CRK"));
+    }
+
+    #[test]
+    fn crank_covers_two_revolutions() {
+        let res = AgRes::DEG_0_1;
+        let out = puml(Some(CrkSigGen::new(&CRK_CONFIGS[2], res).unwrap()), Some(CamSigGen::new(&CAM_CONFIGS[0], res).unwrap()));
+        // Starting high, the last tooth of the cycle ending on the reference
+        assert_eq!(out.matches("CRK is high").count(), 1 + 2 * 58);
+        // Half a tooth of 3° is 10 pixels wide
+        assert!(out.contains("scale 100 as 333 pixels"));
+    }
+}
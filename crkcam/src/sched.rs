@@ -0,0 +1,800 @@
+//! Scheduling of the crank and cam events in timer ticks, independent of the
+//! timer generating them: generators are stepped on each compare match and
+//! the time and output edge of the next one is returned to the timer.
+use super::cam::CamSigGen;
+use super::cmn::{AgRes, Direction, Edge, Event, Polarity, CRPM_PER_RPM, REV_DEG_TICKS};
+use super::crk::{CrkMode, CrkSigGen};
+use super::profile::ProfilePlayer;
+
+const TIM_MIN_FROM_S: u32 = 60;
+/// Generation speed range, 0.01 rpm
+pub const SPEED_MIN_CRPM: u32 = CRPM_PER_RPM;
+pub const SPEED_MAX_CRPM: u32 = 12_000 * CRPM_PER_RPM;
+/// Minimal time between two compares of a channel, µs, for the compare interrupt 
+/// to program the next one before the counter reaches it
+pub const OC_DT_MIN_US: u64 = 2;
+
+/// Next output change of a compare channel
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OcCmd {
+    /// Timer ticks since the previous compare match of the channel, at least `OC_DT_MIN_US` µs
+    pub dt: u64,
+    /// Output edge to generate, or None to leave the output unchanged
+    pub edge: Option<Edge>,
+}
+
+impl OcCmd {
+    fn new(dt: u64, edge: Option<Edge>) -> OcCmd {
+        // An event cannot happen at the same time as the previous one
+        OcCmd { dt: dt.max(1), edge }
+    }
+}
+
+/// Ratio converting angles in degree ticks to timer ticks
+#[derive(Copy, Clone)]
+struct AgScale {
+    num: u64,
+    den: u64,
+}
+
+/// Speed ramp towards a target speed
+#[derive(Copy, Clone)]
+struct Ramp {
+    /// Target speed, 0.01 rpm
+    target: u32,
+    /// Ramp rate, 0.01 rpm/s
+    rate: u32,
+    /// Speed change not applied yet, 0.01 rpm times timer ticks
+    acc: u64,
+}
+
+/// Cam phase moved towards a target at a slew rate, as by a variable valve timing actuator
+#[derive(Copy, Clone)]
+struct Vvt {
+    /// Target offset, degree ticks
+    target: i32,
+    /// Slew rate, degree ticks per second
+    rate: u32,
+    /// Offset change not applied yet, degree ticks times timer ticks
+    acc: u64,
+    /// Amplitude of the triangle oscillation of the target, degree ticks
+    osc_amp: u32,
+    /// Period of the oscillation, timer ticks, 0 if none
+    osc_period: u64,
+    /// Time in the oscillation period, timer ticks
+    osc_t: u64,
+}
+
+impl Vvt {
+    /// Target offset at the current time of the oscillation
+    fn goal(&self) -> i32 {
+        if self.osc_period == 0 {
+            return self.target;
+        }
+        // Triangle starting upwards from the target
+        let amp = self.osc_amp as i64;
+        let x = (self.osc_t as u128 * 4 * amp as u128 / self.osc_period as u128) as i64;
+        let osc = if x < amp {
+            x
+        } else if x < 3 * amp {
+            2 * amp - x
+        } else {
+            x - 4 * amp
+        };
+        self.target + osc as i32
+    }
+}
+
+/// Number of cam outputs of the scheduler
+pub const CAM_NR_MAX: usize = 4;
+
+/// Generation state of a cam output
+struct CamCh {
+    ///Generator of the output, None leaving it idle
+    gen: Option<CamSigGen>,
+    ///Generator waiting for the crank reference to replace the current one, None 
+    ///disabling the output
+    pending: Option<Option<CamSigGen>>,
+    ev: Event,
+    ///Remainder of the conversion of event angles to timer ticks
+    frac: u64,
+    ///Phase offset to the crank, degree ticks, positive delaying the cam
+    offset: i32,
+    ///Phase offset applied to the compares scheduled so far
+    shift: i32,
+    ///Correction of the angle of the next event, moved by offset changes and 
+    ///shortened by the compares scheduled since the last one
+    lead: i32,
+    ///Position of the scheduled compare in the 720° cycle, 0 being the crank reference
+    ag: u32,
+    ///Angle between the last event and the scheduled compare
+    since: u32,
+    ///Timer ticks the scheduled compare is late, compares being at least the minimal 
+    ///distance apart, negative if it's early
+    late: i64,
+    ///Timer ticks between the last two compares
+    dt: u64,
+    ///Time of the scheduled compare, timer ticks since the start
+    t: u64,
+    ///Scheduled compare moved by a speed change and not reported to the timer yet
+    moved: bool,
+    ///Phase variation in progress, if any
+    vvt: Option<Vvt>,
+}
+
+/// Idle cam output, without generator nor offset
+const CAM_CH_IDLE: CamCh = CamCh {
+    gen: None,
+    pending: None,
+    ev: Event::new(),
+    frac: 0,
+    offset: 0,
+    shift: 0,
+    lead: 0,
+    ag: 0,
+    since: 0,
+    late: 0,
+    dt: 0,
+    t: 0,
+    moved: false,
+    vvt: None,
+};
+
+impl CamCh {
+    /// Correction of the angle of the first event from the reference, offsetting the cam
+    fn ref_lead(&self, dir: Direction) -> i32 {
+        match dir {
+            Direction::Forward => self.shift,
+            Direction::Reverse => -self.shift,
+        }
+    }
+
+    /// Restart from the reference, the offset applying from the first event
+    fn restart(&mut self, dir: Direction) {
+        self.frac = 0;
+        self.shift = self.offset;
+        self.lead = self.ref_lead(dir);
+        self.ag = 0;
+        self.since = 0;
+        self.late = 0;
+        self.dt = 0;
+        self.t = 0;
+        self.moved = false;
+    }
+
+    /// Move the phase offset towards the target of the phase variation by the change 
+    /// accumulated since the last compare
+    fn vvt_step(&mut self, tim_clk: u64) {
+        let vvt = match self.vvt.as_mut() {
+            Some(vvt) => vvt,
+            None => return,
+        };
+        if vvt.osc_period > 0 {
+            vvt.osc_t = (vvt.osc_t + self.dt) % vvt.osc_period;
+        }
+        let goal = vvt.goal();
+        if goal == self.offset {
+            vvt.acc = 0;
+            return;
+        }
+        vvt.acc += vvt.rate as u64 * self.dt;
+        let inc = (vvt.acc / tim_clk).min(i32::MAX as u64) as i32;
+        vvt.acc %= tim_clk;
+        self.offset = if self.offset < goal {
+            self.offset.saturating_add(inc).min(goal)
+        } else {
+            self.offset.saturating_sub(inc).max(goal)
+        };
+    }
+
+    /// Next compare, once the previous one matched, in a cycle of `cycle` degree ticks, 
+    /// at least `dt_min` timer ticks after the previous one
+    fn next(&mut self, dir: Direction, cycle: u32, scale: AgScale, dt_min: u64) -> OcCmd {
+        // Delaying the cam moves its next event later forward, earlier in reverse
+        let delta = self.offset - self.shift;
+        self.shift = self.offset;
+        self.lead += match dir {
+            Direction::Forward => delta,
+            Direction::Reverse => -delta,
+        };
+        let ref_ag = match (dir, self.ag) {
+            (Direction::Forward, pos) => cycle - pos,
+            (Direction::Reverse, 0) => cycle,
+            (Direction::Reverse, pos) => pos,
+        };
+
+        let cam = match self.gen.as_mut() {
+            Some(cam) => cam,
+            None => {
+                // Idle outputs stop on each reference, where a generator can take over
+                self.ag = 0;
+                return self.cmd(ref_ag, Some(Edge::Falling), scale, dt_min);
+            }
+        };
+        let mut next_ag = cam.next_ag() as i32 + self.lead;
+        if next_ag < 0 {
+            // Events moved before the last compare only set the level they end on, as soon 
+            // as the timer can, the following compares catching up
+            while next_ag < 0 {
+                self.ev = cam.next().unwrap();
+                self.lead = next_ag;
+                next_ag += cam.next_ag() as i32;
+            }
+            self.since = self.lead.unsigned_abs();
+            return self.cmd(0, gen_edge(&self.ev), scale, dt_min);
+        }
+        let ag = next_ag as u32;
+        if ag > ref_ag {
+            // Event beyond the crank reference, stop on it first
+            self.lead -= ref_ag as i32;
+            self.since += ref_ag;
+            self.ag = 0;
+            return self.cmd(ref_ag, None, scale, dt_min);
+        }
+
+        // Get event from the cam list
+        self.ev = cam.next().unwrap();
+        self.lead = 0;
+        self.since = 0;
+        self.ag = match dir {
+            Direction::Forward => (self.ag + ag) % cycle,
+            Direction::Reverse => (ref_ag - ag) % cycle,
+        };
+        self.cmd(ag, gen_edge(&self.ev), scale, dt_min)
+    }
+
+    /// Compare `ag` degree ticks after the previous one. Compares closer than `dt_min` 
+    /// timer ticks to the previous one are late, the following ones catching up.
+    fn cmd(&mut self, ag: u32, edge: Option<Edge>, scale: AgScale, dt_min: u64) -> OcCmd {
+        let dt = Scheduler::ag_to_ticks(ag, scale, &mut self.frac) as i64 - self.late;
+        let cmd = OcCmd::new(dt.max(dt_min as i64) as u64, edge);
+        self.late = cmd.dt as i64 - dt;
+        self.dt = cmd.dt;
+        self.t += cmd.dt;
+        self.moved = false;
+        cmd
+    }
+
+    /// Move the scheduled compare for the angle left to it from the crank event handled 
+    /// to be covered at a new speed. The crank compare matched at `now` timer ticks, 
+    /// `crk_late` late, with the conversion remainder `crk_frac`, the scales `old` and 
+    /// `new` converting angles at the previous and new speeds.
+    fn rescale(&mut self, now: u64, crk_late: u64, crk_frac: u64, old: AgScale, new: AgScale, dt_min: u64) {
+        // Angle left from the crank event, in units of the scales sharing their numerator
+        let crk_t = now as i64 - crk_late as i64;
+        let left = (self.t as i64 - self.late - crk_t) as i128 * old.den as i128 + self.frac as i128
+            - crk_frac as i128;
+        if left <= 0 {
+            // Compare due with the crank event
+            return;
+        }
+        let units = left as u128 + crk_frac as u128;
+        let exact = crk_t + (units / new.den as u128) as i64;
+        self.frac = (units % new.den as u128) as u64;
+        // The compare can't move before the crank event handled, nor closer to the previous 
+        // one than the minimal distance
+        let prev = self.t - self.dt;
+        let t = exact.max(now as i64).max((prev + dt_min) as i64) as u64;
+        self.late = t as i64 - exact;
+        self.dt = t - prev;
+        self.t = t;
+        self.moved = true;
+    }
+}
+
+pub struct Scheduler {
+    crk: Option<CrkSigGen>,
+    cams: [CamCh; CAM_NR_MAX],
+    ///Crank generator waiting for the crank reference to replace the current one
+    pending_crk: Option<CrkSigGen>,
+    ///Direction waiting for the crank reference to be applied
+    pending_dir: Option<Direction>,
+    ///Crank output mode waiting for the crank reference to be applied
+    pending_crk_mode: Option<CrkMode>,
+    ///Output polarity changed and not reported to the timer yet
+    pol_changed: bool,
+    crk_ev: Event,
+    ///Remainder of the conversion of event angles to timer ticks, carried to the
+    ///next event so that the average period is exact
+    crk_frac: u64,
+    ///Crank pulse started, timer ticks between its end and the next event
+    crk_pulse_rem: Option<u64>,
+    ///Timer ticks the scheduled crank compare is late, crank pulses ending after the next 
+    ///event or compares being at least the minimal distance apart
+    crk_late: u64,
+    ///Time of the scheduled crank compare, timer ticks since the start
+    crk_t: u64,
+    ///Time of the last crank event handled, timer ticks since the start
+    crk_ev_t: u64,
+    ///Generation speed, 0.01 rpm
+    speed: u32,
+    ///Speed waiting for the next crank event to be applied
+    pending_speed: Option<u32>,
+    ///Speed ramp in progress, if any
+    ramp: Option<Ramp>,
+    ///Speed profile played, if any, advanced in timer ticks
+    player: Option<ProfilePlayer>,
+    ///Timer clock frequency, Hz
+    tim_clk: u64,
+    ///Minimal timer ticks between two compares of a channel
+    dt_min: u64,
+    ///Resolution of the event angles of the generators
+    res: AgRes,
+}
+
+impl Scheduler {
+    /// Scheduler of a timer clocked at `tim_clk` Hz, for generators of resolution `res`
+    pub const fn new(tim_clk: u64, res: AgRes) -> Scheduler {
+        Scheduler {
+            crk: None,
+            cams: [CAM_CH_IDLE; CAM_NR_MAX],
+            pending_crk: None,
+            pending_dir: None,
+            pending_crk_mode: None,
+            pol_changed: false,
+            crk_ev: Event::new(),
+            crk_frac: 0,
+            crk_pulse_rem: None,
+            crk_late: 0,
+            crk_t: 0,
+            crk_ev_t: 0,
+            speed: SPEED_MIN_CRPM,
+            pending_speed: None,
+            ramp: None,
+            player: None,
+            tim_clk,
+            dt_min: tim_clk * OC_DT_MIN_US / 1_000_000,
+            res,
+        }
+    }
+
+    /// Set the generators of the crank and of the first cam output, replacing the 
+    /// current ones immediately
+    pub fn initialize(&mut self, cam: CamSigGen, crk: CrkSigGen) {
+        self.check_res(crk.res());
+        self.crk = Some(crk);
+        self.initialize_cam(0, Some(cam));
+    }
+
+    /// Set the generator of cam output `idx`, replacing the current one immediately, 
+    /// None leaving the output idle
+    pub fn initialize_cam(&mut self, idx: usize, cam: Option<CamSigGen>) {
+        if let Some(cam) = cam.as_ref() {
+            self.check_res(cam.res());
+        }
+        self.cams[idx].gen = cam;
+        self.pol_changed = true;
+    }
+
+    /// Replace the generators of the crank and of the first cam output once the 
+    /// current ones reach the reference
+    pub fn set_sig_gen(&mut self, cam: CamSigGen, crk: CrkSigGen) {
+        self.check_res(crk.res());
+        self.pending_crk = Some(crk);
+        self.set_cam_sig_gen(0, Some(cam));
+    }
+
+    /// Replace the generator of cam output `idx` once the generators reach the reference, 
+    /// None disabling the output
+    pub fn set_cam_sig_gen(&mut self, idx: usize, cam: Option<CamSigGen>) {
+        if let Some(cam) = cam.as_ref() {
+            self.check_res(cam.res());
+        }
+        self.cams[idx].pending = Some(cam);
+    }
+
+    /// Generators shall be created with the resolution of the scheduler
+    fn check_res(&self, res: AgRes) {
+        debug_assert!(res == self.res);
+    }
+
+    /// Resolution of the event angles of the generators
+    pub fn res(&self) -> AgRes {
+        self.res
+    }
+
+    /// Change the direction once the generators reach the reference
+    pub fn set_direction(&mut self, dir: Direction) {
+        self.pending_dir = Some(dir);
+    }
+
+    /// Change the crank mode once the generators reach the reference
+    pub fn set_crk_mode(&mut self, mode: CrkMode) {
+        self.pending_crk_mode = Some(mode);
+    }
+
+    /// Degree ticks of the generation per degree tick of the configurations
+    fn cfg_ticks(&self) -> u32 {
+        self.res.rev_ticks() / REV_DEG_TICKS
+    }
+
+    /// Set the phase offset of cam output `idx` to the crank, 0.1°, a positive offset 
+    /// delaying the cam, stopping any phase variation. It's applied from the next cam event.
+    pub fn set_cam_offset(&mut self, idx: usize, offset: i32) {
+        let offset = offset * self.cfg_ticks() as i32;
+        let ch = &mut self.cams[idx];
+        ch.vvt = None;
+        ch.offset = offset;
+    }
+
+    /// Move the phase offset of cam output `idx` towards `target`, 0.1°, at `rate` °/s, 
+    /// the target oscillating by `osc_amp` 0.1° over `osc_period_ms` if both are set. 
+    /// The offset is updated on each cam event, a rate of 0 setting it immediately.
+    pub fn set_cam_vvt(&mut self, idx: usize, target: i32, rate: u32, osc_amp: u32, osc_period_ms: u32) {
+        if rate == 0 {
+            self.set_cam_offset(idx, target);
+            return;
+        }
+        let cfg_ticks = self.cfg_ticks();
+        let osc_period = if osc_amp == 0 { 0 } else { osc_period_ms as u64 * self.tim_clk / 1_000 };
+        self.cams[idx].vvt = Some(Vvt {
+            target: target * cfg_ticks as i32,
+            rate: rate * 10 * cfg_ticks,
+            acc: 0,
+            osc_amp: osc_amp * cfg_ticks,
+            osc_period,
+            osc_t: 0,
+        });
+    }
+
+    /// Phase offset of cam output `idx` to the crank, 0.1°
+    pub fn cam_offset(&self, idx: usize) -> i32 {
+        self.cams[idx].offset / self.cfg_ticks() as i32
+    }
+
+    /// Rotation direction of the generators
+    fn direction(&self) -> Direction {
+        self.crk.as_ref().map_or(Direction::Forward, |crk| crk.direction())
+    }
+
+    /// Check if generators, direction or crank mode changes are still waiting to be applied
+    pub fn is_pending(&self) -> bool {
+        self.pending_crk.is_some()
+            || self.cams.iter().any(|ch| ch.pending.is_some())
+            || self.pending_dir.is_some()
+            || self.pending_crk_mode.is_some()
+    }
+
+    /// Set the output polarity of the crank and of cam output `idx`, effective immediately, 
+    /// an idle cam output staying normal
+    pub fn set_polarity(&mut self, crk: Polarity, idx: usize, cam: Polarity) {
+        if let Some(gen) = self.crk.as_mut() {
+            gen.set_polarity(crk);
+        }
+        if let Some(gen) = self.cams[idx].gen.as_mut() {
+            gen.set_polarity(cam);
+        }
+        self.pol_changed = true;
+    }
+
+    /// Output polarity of the crank and of the cams, idle cams being normal
+    pub fn polarity(&self) -> (Polarity, [Polarity; CAM_NR_MAX]) {
+        let crk = self.crk.as_ref().map_or(Polarity::Normal, |crk| crk.polarity());
+        let cams = self.cams.each_ref().map(|ch| ch.gen.as_ref().map_or(Polarity::Normal, |cam| cam.polarity()));
+        (crk, cams)
+    }
+
+    /// Check if the output polarity changed since the last call, the timer
+    /// having to apply it
+    pub fn take_pol_changed(&mut self) -> bool {
+        core::mem::replace(&mut self.pol_changed, false)
+    }
+
+    /// Number of timer ticks per second
+    pub fn tick_freq(&self) -> u64 {
+        self.tim_clk
+    }
+
+    /// Convert a duration in µs to timer ticks
+    fn us_to_ticks(&self, us: u16) -> u64 {
+        us as u64 * self.tim_clk / 1_000_000
+    }
+
+    /// Conversion of angles in degree ticks to timer ticks at the current speed
+    fn ag_scale(&self) -> AgScale {
+        AgScale {
+            num: self.tim_clk * (TIM_MIN_FROM_S * CRPM_PER_RPM) as u64,
+            den: self.speed as u64 * self.res.rev_ticks() as u64,
+        }
+    }
+
+    /// Convert an angle in degree ticks to timer ticks. The remainder is carried
+    /// to the next conversion, dithering periods by one tick to keep their average exact.
+    fn ag_to_ticks(ag: u32, scale: AgScale, frac: &mut u64) -> u64 {
+        let num = ag as u64 * scale.num + *frac;
+        *frac = num % scale.den;
+        num / scale.den
+    }
+
+    /// Set the generation speed, 0.01 rpm, on the crank event handled
+    fn update_speed(&mut self, spd: u32) {
+        let old = self.ag_scale();
+        // Speed is limited to its range, the minimum avoiding a division by 0
+        self.speed = spd.clamp(SPEED_MIN_CRPM, SPEED_MAX_CRPM);
+
+        // Counter and generators continue, the new speed applies from the crank event: 
+        // the crank remainder is an angle, kept as is, and the scheduled cam compares 
+        // are moved for the cams to keep their phase to the crank
+        let new = self.ag_scale();
+        let (now, crk_late, crk_frac, dt_min) = (self.crk_t, self.crk_late, self.crk_frac, self.dt_min);
+        for ch in self.cams.iter_mut() {
+            ch.rescale(now, crk_late, crk_frac, old, new, dt_min);
+        }
+    }
+
+    /// Set the speed, 0.01 rpm, stopping any ramp or profile. It's applied from the 
+    /// next crank event.
+    pub fn set_speed_crpm(&mut self, spd: u32) {
+        self.ramp = None;
+        self.player = None;
+        self.pending_speed = Some(spd.clamp(SPEED_MIN_CRPM, SPEED_MAX_CRPM));
+    }
+
+    /// Ramp the speed towards `target`, 0.01 rpm, at `rate` rpm/s
+    pub fn set_speed_ramp(&mut self, target: u32, rate: u32) {
+        if rate == 0 {
+            self.set_speed_crpm(target);
+        } else {
+            self.player = None;
+            self.ramp = Some(Ramp {
+                target: target.clamp(SPEED_MIN_CRPM, SPEED_MAX_CRPM),
+                rate: rate * CRPM_PER_RPM,
+                acc: 0,
+            });
+        }
+    }
+
+    /// Play a speed profile, or stop the one playing if None
+    pub fn set_profile(&mut self, player: Option<ProfilePlayer>) {
+        self.ramp = None;
+        self.player = player;
+        if let Some(spd) = self.player.as_ref().map(|player| player.speed()) {
+            self.pending_speed = Some(spd.clamp(SPEED_MIN_CRPM, SPEED_MAX_CRPM));
+        }
+    }
+
+    pub fn pause_profile(&mut self, paused: bool) {
+        if let Some(player) = self.player.as_mut() {
+            player.set_paused(paused);
+        }
+    }
+
+    /// Advance the speed profile played by `dt` timer ticks, up to the crank event handled
+    fn profile_step(&mut self, dt: u64) {
+        if let Some(spd) = self.player.as_mut().and_then(|player| player.advance(dt)) {
+            self.update_speed(spd);
+        }
+    }
+
+    /// Move the speed towards the ramp target by the change accumulated
+    /// during the `dt` timer ticks up to the crank event handled
+    fn ramp_step(&mut self, dt: u64) {
+        let tim_clk = self.tim_clk;
+        let ramp = match self.ramp.as_mut() {
+            Some(ramp) => ramp,
+            None => return,
+        };
+        ramp.acc += ramp.rate as u64 * dt;
+        let inc = (ramp.acc / tim_clk).min(u32::MAX as u64) as u32;
+        ramp.acc %= tim_clk;
+
+        let target = ramp.target;
+        let spd = if self.speed < target {
+            self.speed.saturating_add(inc).min(target)
+        } else {
+            self.speed.saturating_sub(inc).max(target)
+        };
+        if spd == target {
+            self.ramp = None;
+        }
+        if spd != self.speed {
+            self.update_speed(spd);
+        }
+    }
+
+    /// Generation speed, 0.01 rpm, including a speed waiting for the next crank event. 
+    /// It's the requested speed, limited to the range supported, period dithering making 
+    /// it exact on average.
+    pub fn speed_crpm(&self) -> u32 {
+        self.pending_speed.unwrap_or(self.speed)
+    }
+
+    /// One timer tick relative to the period of one degree at the generation speed, ppm. 
+    /// Periods being dithered by one tick, it bounds the deviation of a degree from its 
+    /// average, not the error of the speed, exact on average.
+    pub fn deg_tick_ppm(&self) -> u32 {
+        let scale = AgScale { den: self.speed_crpm() as u64 * self.res.rev_ticks() as u64, ..self.ag_scale() };
+        let deg_ticks = scale.num * (self.res.rev_ticks() / 360) as u64 / scale.den;
+        (1_000_000 / deg_ticks.max(1)) as u32
+    }
+
+    /// Apply pending generators, direction and crank mode, if any, once the crank
+    /// and all the cams reached the reference. The new generators continue seamlessly
+    /// from the reference, the cams keeping their offset.
+    fn apply_pending(&mut self) {
+        let at_ref = match self.crk.as_ref() {
+            Some(crk) => crk.is_at_ref() && self.cams.iter().all(|ch| ch.ag == 0),
+            None => true,
+        };
+        if !at_ref {
+            return;
+        }
+
+        if let Some(mut crk) = self.pending_crk.take() {
+            // New generators continue in the direction and mode of the current ones
+            if let Some(cur) = self.crk.as_ref() {
+                crk.set_direction(cur.direction());
+                crk.set_mode(cur.mode());
+            }
+            self.crk = Some(crk);
+            self.pol_changed = true;
+        }
+        let dir = self.direction();
+        for ch in self.cams.iter_mut() {
+            if let Some(mut cam) = ch.pending.take() {
+                if let Some(cam) = cam.as_mut() {
+                    cam.set_direction(dir);
+                }
+                ch.gen = cam;
+                ch.lead = ch.ref_lead(dir);
+                ch.since = 0;
+                self.pol_changed = true;
+            }
+        }
+
+        if let (Some(dir), Some(crk)) = (self.pending_dir.take(), self.crk.as_mut()) {
+            if dir != crk.direction() {
+                // All wheels turn around by the same angle to keep their phase, the cams 
+                // being past their last event by their offset
+                let turn_ag = self.cams.iter()
+                    .filter_map(|ch| ch.gen.as_ref().map(|cam| (cam.next_ag() as i32 + ch.lead).max(0) as u32))
+                    .fold(crk.turn_ag_max(), u32::min);
+                crk.turn_around(turn_ag);
+                // The cams cross the reference again after turning around
+                let cycle = 2 * self.res.rev_ticks();
+                for ch in self.cams.iter_mut() {
+                    if let Some(cam) = ch.gen.as_mut() {
+                        cam.turn_around(turn_ag + ch.since);
+                        ch.lead = 0;
+                    }
+                    ch.ag = match dir {
+                        Direction::Forward => (cycle - turn_ag) % cycle,
+                        Direction::Reverse => turn_ag % cycle,
+                    };
+                }
+            }
+        }
+
+        if let (Some(mode), Some(crk)) = (self.pending_crk_mode.take(), self.crk.as_mut()) {
+            crk.set_mode(mode);
+        }
+    }
+
+    /// First compare of the crank and of the cams, counted from the timer start
+    pub fn start(&mut self) -> (OcCmd, [OcCmd; CAM_NR_MAX]) {
+        self.crk_frac = 0;
+        self.crk_pulse_rem = None;
+        self.crk_late = 0;
+        self.crk_t = 0;
+        self.crk_ev_t = 0;
+        if let Some(spd) = self.pending_speed.take() {
+            self.speed = spd;
+        }
+        let dir = self.direction();
+        for ch in self.cams.iter_mut() {
+            ch.restart(dir);
+        }
+
+        self.crk_ev = self.crk.as_mut().unwrap().next().unwrap();
+        let crk_dt = Self::ag_to_ticks(self.crk_ev.ag, self.ag_scale(), &mut self.crk_frac);
+        let crk = self.crk_cmd(crk_dt, gen_edge(&self.crk_ev));
+
+        (crk, core::array::from_fn(|idx| self.next_cam(idx)))
+    }
+
+    /// Next crank compare, once the previous one matched
+    pub fn next_crk(&mut self) -> OcCmd {
+        if let Some(rem) = self.crk_pulse_rem.take() {
+            // Pulse ended, program the event following it
+            return self.crk_cmd(rem, gen_edge(&self.crk_ev));
+        }
+
+        // Reference reached, swap generators, direction or mode if requested
+        self.apply_pending();
+        // Speed changes, ramps and profiles are applied on each crank event, the next 
+        // one using the new speed
+        let elapsed = self.crk_t - self.crk_ev_t;
+        self.crk_ev_t = self.crk_t;
+        if let Some(spd) = self.pending_speed.take() {
+            self.update_speed(spd);
+        }
+        self.ramp_step(elapsed);
+        self.profile_step(elapsed);
+        let pulse_us = self.crk_ev.pulse_us;
+        // Get event from the crk list
+        let crk = self.crk.as_mut().unwrap();
+        self.crk_ev = crk.next().unwrap();
+        let next_ag = crk.next_ag();
+        let scale = self.ag_scale();
+        let dt = Self::ag_to_ticks(self.crk_ev.ag, scale, &mut self.crk_frac);
+        match pulse_us {
+            Some(us) => {
+                // Pulse started, end it before the next tooth: the event following the pulse 
+                // is the other half of the tooth, which isn't generated. Pulses too long for 
+                // the tooth are shortened, the output staying low the minimal compare distance.
+                let mut frac = self.crk_frac;
+                let tooth_dt = dt + Self::ag_to_ticks(next_ag, scale, &mut frac);
+                let width = self.us_to_ticks(us).min(tooth_dt.saturating_sub(self.dt_min)).max(self.dt_min);
+                if width < dt {
+                    self.crk_pulse_rem = Some(dt - width);
+                } else {
+                    // Pulse ending after the other half of the tooth, which is then skipped
+                    self.crk_late += width - dt;
+                }
+                self.crk_t += width;
+                OcCmd::new(width, Some(Edge::Falling))
+            }
+            None => self.crk_cmd(dt, gen_edge(&self.crk_ev)),
+        }
+    }
+
+    /// Crank compare `dt` timer ticks after the previous one. Compares closer than the 
+    /// minimal distance to the previous one are late, the following ones catching up.
+    fn crk_cmd(&mut self, dt: u64, edge: Option<Edge>) -> OcCmd {
+        let dt = dt as i64 - self.crk_late as i64;
+        let cmd = OcCmd::new(dt.max(self.dt_min as i64) as u64, edge);
+        self.crk_late = (cmd.dt as i64 - dt) as u64;
+        self.crk_t += cmd.dt;
+        cmd
+    }
+
+    /// Report the last crank compare to be programmed `ticks` timer ticks later than 
+    /// scheduled, the timer not being able to program it in time. The following 
+    /// compares catch up.
+    pub fn delay_crk(&mut self, ticks: u64) {
+        self.crk_late += ticks;
+        self.crk_t += ticks;
+    }
+
+    /// Report the last compare of cam output `idx` to be programmed `ticks` timer ticks 
+    /// later than scheduled, like `delay_crk`, negative if it matched before 
+    /// the time a speed change moved it to
+    pub fn delay_cam(&mut self, idx: usize, ticks: i64) {
+        let ch = &mut self.cams[idx];
+        ch.late += ticks;
+        ch.dt = (ch.dt as i64 + ticks) as u64;
+        ch.t = (ch.t as i64 + ticks) as u64;
+    }
+
+    /// Compare of cam output `idx` moved by a speed change since the last call, timer ticks 
+    /// after its previous compare, the timer having to reprogram it
+    pub fn take_cam_moved(&mut self, idx: usize) -> Option<u64> {
+        let ch = &mut self.cams[idx];
+        core::mem::replace(&mut ch.moved, false).then_some(ch.dt)
+    }
+
+    /// Next compare of cam output `idx`, once the previous one matched
+    ///
+    /// Cam events are moved by the offset of the output, and the cam stops on the crank 
+    /// reference when an event crosses it, for changes applied on the reference to find 
+    /// all wheels there. Idle outputs only stop on the reference.
+    pub fn next_cam(&mut self, idx: usize) -> OcCmd {
+        let dir = self.direction();
+        let cycle = 2 * self.res.rev_ticks();
+        let scale = self.ag_scale();
+        let (tim_clk, dt_min) = (self.tim_clk, self.dt_min);
+        let ch = &mut self.cams[idx];
+        // Phase variations are updated on each cam compare, the next one using the new offset
+        ch.vvt_step(tim_clk);
+        ch.next(dir, cycle, scale, dt_min)
+    }
+}
+
+/// Output edge to generate for an event, if any
+fn gen_edge(ev: &Event) -> Option<Edge> {
+    if ev.is_gen {
+        Some(ev.edge)
+    } else {
+        None
+    }
+}
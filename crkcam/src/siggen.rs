@@ -0,0 +1,36 @@
+use super::cam;
+use super::cmn::{Direction, Polarity, CRPM_PER_RPM};
+use super::crk;
+use super::profile::ProfilePlayer;
+
+pub trait CrkCamSigGen {
+    fn initialize(&mut self, cam : cam::CamSigGen, crk : crk::CrkSigGen);
+    fn set_sig_gen(&mut self, cam : cam::CamSigGen, crk : crk::CrkSigGen);
+    /// Set the generator of cam output `idx`, None leaving the output idle
+    fn initialize_cam(&mut self, idx: usize, cam: Option<cam::CamSigGen>);
+    /// Replace the generator of cam output `idx` on the crank reference, None disabling the output
+    fn set_cam_sig_gen(&mut self, idx: usize, cam: Option<cam::CamSigGen>);
+    fn set_direction(&mut self, dir : Direction);
+    fn set_crk_mode(&mut self, mode : crk::CrkMode);
+    /// Set the polarity of the crank and of cam output `idx`
+    fn set_polarity(&mut self, crk : Polarity, idx: usize, cam : Polarity);
+    /// Set the speed, in 0.01 rpm
+    fn set_speed_crpm(&mut self, spd: u32);
+    /// Ramp the speed towards `target`, in 0.01 rpm, at `rate` rpm/s
+    fn set_speed_ramp(&mut self, target: u32, rate: u32);
+    /// Play a speed profile, or stop the one playing if None
+    fn set_profile(&mut self, player: Option<ProfilePlayer>);
+    fn pause_profile(&mut self, paused: bool);
+    /// Set the phase offset of cam output `idx` to the crank, in 0.1°, a positive offset 
+    /// delaying the cam
+    fn set_cam_offset(&mut self, idx: usize, offset: i32);
+    /// Move the phase offset of cam output `idx` towards `target`, in 0.1°, at `rate` °/s, 
+    /// the target oscillating by `osc_amp` 0.1° over `osc_period_ms` if both are set
+    fn set_cam_vvt(&mut self, idx: usize, target: i32, rate: u32, osc_amp: u32, osc_period_ms: u32);
+    fn set_speed_rpm(&mut self, spd: u32) {
+        self.set_speed_crpm(spd * CRPM_PER_RPM);
+    }
+    fn set_next_crk_ev(&mut self);
+    fn set_next_cam_ev(&mut self, idx: usize);
+    fn start(&mut self);
+}
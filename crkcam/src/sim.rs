@@ -0,0 +1,739 @@
+//! Host simulation of the signal generation: the timer compare channels are
+//! modelled in software, recording the transitions of the outputs instead of
+//! driving pins. Compare matches are handled in time order, on a 64 bits
+//! counter, scheduled by the same code as the firmware.
+use super::cam::CamSigGen;
+use super::cmn::{AgRes, Direction, Edge, Polarity};
+use super::crk::{CrkMode, CrkSigGen};
+use super::profile::ProfilePlayer;
+use super::sched::{OcCmd, Scheduler, CAM_NR_MAX};
+use super::siggen::CrkCamSigGen;
+
+use std::vec::Vec;
+
+/// Timer clock of the Blue Pill generator, Hz
+pub const TIM_CLK: u64 = 72_000_000;
+
+/// Output of the simulated timer
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Ch {
+    Crk,
+    /// Cam output of the given index
+    Cam(usize),
+}
+
+/// Level change of an output
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transition {
+    /// Time of the change, timer ticks since the creation of the timer
+    pub t: u64,
+    pub ch: Ch,
+    /// Output level after the change, true if high
+    pub level: bool,
+}
+
+/// Compare channel of the simulated timer
+#[derive(Copy, Clone)]
+struct SimOc {
+    /// Time of the next compare match, timer ticks
+    t: u64,
+    /// Time of the previous compare match, timer ticks
+    prev: u64,
+    /// Output edge to generate on the next compare match, if any
+    edge: Option<Edge>,
+    /// Output reference level, active high before the polarity is applied
+    active: bool,
+    inverted: bool,
+}
+
+impl SimOc {
+    const fn new() -> SimOc {
+        SimOc {
+            t: 0,
+            prev: 0,
+            edge: None,
+            active: false,
+            inverted: false,
+        }
+    }
+
+    fn level(&self) -> bool {
+        self.active != self.inverted
+    }
+
+    fn on_match(&mut self) {
+        if let Some(edge) = self.edge {
+            self.active = edge == Edge::Rising;
+        }
+    }
+
+    fn schedule(&mut self, cmd: OcCmd) {
+        self.prev = self.t;
+        self.t += cmd.dt;
+        self.edge = cmd.edge;
+    }
+
+    /// Move the next compare match `dt` timer ticks after the previous one
+    fn reschedule(&mut self, dt: u64) {
+        self.t = self.prev + dt;
+    }
+}
+
+/// Software model of the generation timer. Outputs are low until the
+/// polarity of the generators is applied on their initialization.
+pub struct SimTimer {
+    sched: Scheduler,
+    crk_oc: SimOc,
+    cam_oc: [SimOc; CAM_NR_MAX],
+    /// Simulated time, timer ticks
+    now: u64,
+    started: bool,
+    trans: Vec<Transition>,
+}
+
+impl SimTimer {
+    /// Timer clocked at `tim_clk` Hz, for generators of resolution `res`
+    pub fn new(tim_clk: u64, res: AgRes) -> SimTimer {
+        SimTimer {
+            sched: Scheduler::new(tim_clk, res),
+            crk_oc: SimOc::new(),
+            cam_oc: [SimOc::new(); CAM_NR_MAX],
+            now: 0,
+            started: false,
+            trans: Vec::new(),
+        }
+    }
+
+    /// Number of timer ticks per second
+    pub fn tick_freq(&self) -> u64 {
+        self.sched.tick_freq()
+    }
+
+    /// Check if generators, direction or crank mode changes are still waiting to be applied
+    pub fn is_pending(&self) -> bool {
+        self.sched.is_pending()
+    }
+
+    /// Generation speed, 0.01 rpm
+    pub fn speed_crpm(&self) -> u32 {
+        self.sched.speed_crpm()
+    }
+
+    /// Simulated time, timer ticks since the creation of the timer
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Current level of an output, true if high
+    pub fn level(&self, ch: Ch) -> bool {
+        match ch {
+            Ch::Crk => self.crk_oc.level(),
+            Ch::Cam(idx) => self.cam_oc[idx].level(),
+        }
+    }
+
+    /// Transitions recorded so far, in time order
+    pub fn transitions(&self) -> &[Transition] {
+        &self.trans
+    }
+
+    /// Remove and return the transitions recorded so far
+    pub fn take_transitions(&mut self) -> Vec<Transition> {
+        core::mem::take(&mut self.trans)
+    }
+
+    /// Run the generation for `dt` timer ticks, handling the compare matches
+    /// happening until then. Channels matching at the same time are handled
+    /// crank first, then cams in order, as the timer interrupt does.
+    pub fn run(&mut self, dt: u64) {
+        let end = self.now + dt;
+        while self.started {
+            let (cam_idx, cam_oc) = self.cam_oc.iter().enumerate().min_by_key(|(_, oc)| oc.t).unwrap();
+            if self.crk_oc.t <= cam_oc.t {
+                if self.crk_oc.t > end {
+                    break;
+                }
+                self.set_next_crk_ev();
+            } else {
+                if cam_oc.t > end {
+                    break;
+                }
+                self.set_next_cam_ev(cam_idx);
+            }
+        }
+        self.now = end;
+    }
+
+    /// Update an output, recording its transition if its level changes
+    fn update_oc(&mut self, ch: Ch, update: impl FnOnce(&mut SimOc)) {
+        let oc = match ch {
+            Ch::Crk => &mut self.crk_oc,
+            Ch::Cam(idx) => &mut self.cam_oc[idx],
+        };
+        let level = oc.level();
+        update(oc);
+        if oc.level() != level {
+            self.trans.push(Transition {
+                t: self.now,
+                ch,
+                level: oc.level(),
+            });
+        }
+    }
+
+    /// Apply the output polarity of all channels if it changed
+    fn apply_polarity(&mut self) {
+        if !self.sched.take_pol_changed() {
+            return;
+        }
+        let (crk, cams) = self.sched.polarity();
+        self.update_oc(Ch::Crk, |oc| oc.inverted = crk == Polarity::Inverted);
+        for (idx, cam) in cams.iter().enumerate() {
+            self.update_oc(Ch::Cam(idx), |oc| oc.inverted = *cam == Polarity::Inverted);
+        }
+    }
+}
+
+impl CrkCamSigGen for SimTimer {
+    fn initialize(&mut self, cam: CamSigGen, crk: CrkSigGen) {
+        self.sched.initialize(cam, crk);
+        self.apply_polarity();
+    }
+
+    fn set_sig_gen(&mut self, cam: CamSigGen, crk: CrkSigGen) {
+        self.sched.set_sig_gen(cam, crk);
+    }
+
+    fn initialize_cam(&mut self, idx: usize, cam: Option<CamSigGen>) {
+        self.sched.initialize_cam(idx, cam);
+        self.apply_polarity();
+    }
+
+    fn set_cam_sig_gen(&mut self, idx: usize, cam: Option<CamSigGen>) {
+        self.sched.set_cam_sig_gen(idx, cam);
+    }
+
+    fn set_direction(&mut self, dir: Direction) {
+        self.sched.set_direction(dir);
+    }
+
+    fn set_crk_mode(&mut self, mode: CrkMode) {
+        self.sched.set_crk_mode(mode);
+    }
+
+    fn set_polarity(&mut self, crk: Polarity, idx: usize, cam: Polarity) {
+        self.sched.set_polarity(crk, idx, cam);
+        self.apply_polarity();
+    }
+
+    fn set_speed_crpm(&mut self, spd: u32) {
+        self.sched.set_speed_crpm(spd);
+    }
+
+    fn set_speed_ramp(&mut self, target: u32, rate: u32) {
+        self.sched.set_speed_ramp(target, rate);
+    }
+
+    fn set_profile(&mut self, player: Option<ProfilePlayer>) {
+        self.sched.set_profile(player);
+    }
+
+    fn pause_profile(&mut self, paused: bool) {
+        self.sched.pause_profile(paused);
+    }
+
+    fn set_cam_offset(&mut self, idx: usize, offset: i32) {
+        self.sched.set_cam_offset(idx, offset);
+    }
+
+    fn set_cam_vvt(&mut self, idx: usize, target: i32, rate: u32, osc_amp: u32, osc_period_ms: u32) {
+        self.sched.set_cam_vvt(idx, target, rate, osc_amp, osc_period_ms);
+    }
+
+    /// Handle the next compare match of the crank channel
+    fn set_next_crk_ev(&mut self) {
+        self.now = self.crk_oc.t;
+        self.update_oc(Ch::Crk, SimOc::on_match);
+        let cmd = self.sched.next_crk();
+        self.crk_oc.schedule(cmd);
+        // Speed changes move the scheduled cam compares
+        for (idx, oc) in self.cam_oc.iter_mut().enumerate() {
+            if let Some(dt) = self.sched.take_cam_moved(idx) {
+                oc.reschedule(dt);
+            }
+        }
+        // Generators may have been swapped on the reference
+        self.apply_polarity();
+    }
+
+    /// Handle the next compare match of a cam channel
+    fn set_next_cam_ev(&mut self, idx: usize) {
+        self.now = self.cam_oc[idx].t;
+        self.update_oc(Ch::Cam(idx), SimOc::on_match);
+        let cmd = self.sched.next_cam(idx);
+        self.cam_oc[idx].schedule(cmd);
+    }
+
+    fn start(&mut self) {
+        let (crk, cams) = self.sched.start();
+        self.crk_oc.t = self.now;
+        self.crk_oc.schedule(crk);
+        for (oc, cam) in self.cam_oc.iter_mut().zip(cams) {
+            oc.t = self.now;
+            oc.schedule(cam);
+        }
+        self.started = true;
+    }
+}
+
+/// Simulate the generation of `crk` and `cams`, on the cam outputs in order, at `spd` 
+/// 0.01 rpm during `dur_us` µs, returning the transitions of the outputs. At least one 
+/// and at most `CAM_NR_MAX` cams are generated.
+pub fn simulate(cams: Vec<CamSigGen>, crk: CrkSigGen, spd: u32, dur_us: u64) -> Vec<Transition> {
+    let mut tim = SimTimer::new(TIM_CLK, crk.res());
+    let mut cams = cams.into_iter();
+    tim.initialize(cams.next().unwrap(), crk);
+    for (idx, cam) in cams.enumerate() {
+        tim.initialize_cam(idx + 1, Some(cam));
+    }
+    tim.set_speed_crpm(spd);
+    tim.start();
+    tim.run(dur_us * TIM_CLK / 1_000_000);
+    tim.take_transitions()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cam_cfg::CAM_CONFIGS;
+    use crate::cmn::CRPM_PER_RPM;
+    use crate::crk::CrkCfg;
+    use crate::crk_cfg::CRK_CONFIGS;
+    use crate::profile_cfg::PROFILES;
+    use crate::sched::{OC_DT_MIN_US, SPEED_MIN_CRPM};
+
+    fn gens() -> (CamSigGen, CrkSigGen) {
+        (CamSigGen::new(&CAM_CONFIGS[0], AgRes::DEG_0_01).unwrap(), crk_60_2())
+    }
+
+    /// 60-2 crank generator
+    fn crk_60_2() -> CrkSigGen {
+        CrkSigGen::new(&CRK_CONFIGS[2], AgRes::DEG_0_01).unwrap()
+    }
+
+    /// Timer generating `crk` and `CAM_CONFIGS[cams[idx]]` on cam output `idx`, in the crank 
+    /// direction, at `rpm`, started once set up by `setup`
+    fn start_sim(crk: CrkSigGen, cams: &[usize], rpm: u32, setup: impl FnOnce(&mut SimTimer)) -> SimTimer {
+        let (res, dir) = (crk.res(), crk.direction());
+        let mut tim = SimTimer::new(TIM_CLK, res);
+        let mut crk = Some(crk);
+        for (idx, &cam_cfg) in cams.iter().enumerate() {
+            let mut cam = CamSigGen::new(&CAM_CONFIGS[cam_cfg], res).unwrap();
+            cam.set_direction(dir);
+            match crk.take() {
+                Some(crk) => tim.initialize(cam, crk),
+                None => tim.initialize_cam(idx, Some(cam)),
+            }
+        }
+        tim.set_speed_rpm(rpm);
+        setup(&mut tim);
+        tim.start();
+        tim
+    }
+
+    /// Times of the rising edges of an output
+    fn rising(trans: &[Transition], ch: Ch) -> Vec<u64> {
+        trans.iter().filter(|tr| tr.ch == ch && tr.level).map(|tr| tr.t).collect()
+    }
+
+    #[test]
+    fn crank_teeth_follow_speed() {
+        let (cam, crk) = gens();
+        // 1000 rpm, 60 ms per revolution
+        let trans = simulate(vec![cam], crk, 1_000 * CRPM_PER_RPM, 120_000);
+        let rev = TIM_CLK * 60 / 1_000;
+
+        let edges = rising(&trans, Ch::Crk);
+        assert_eq!(edges.len(), 2 * 58);
+        let periods: Vec<u64> = edges.windows(2).map(|w| w[1] - w[0]).collect();
+        assert!(periods.iter().all(|&dt| dt == rev / 60 || dt == 3 * rev / 60));
+        assert_eq!(periods.iter().filter(|&&dt| dt == 3 * rev / 60).count(), 1);
+        assert_eq!(edges[58] - edges[0], rev);
+    }
+
+    #[test]
+    fn dithering_keeps_speed_exact() {
+        let (cam, crk) = gens();
+        // 999.99 rpm, revolutions aren't a whole number of timer ticks
+        let spd = 99_999;
+        let trans = simulate(vec![cam], crk, spd, 1_000_000);
+        let num = TIM_CLK * 60 * CRPM_PER_RPM as u64;
+
+        let edges = rising(&trans, Ch::Crk);
+        for (rev, t) in edges.iter().step_by(58).enumerate() {
+            let exact = edges[0] + rev as u64 * num / spd as u64;
+            assert!(t.abs_diff(exact) <= 1, "revolution {}", rev);
+        }
+    }
+
+    #[test]
+    fn delayed_compares_are_caught_up() {
+        let sched = || {
+            let (cam, crk) = gens();
+            let mut sched = Scheduler::new(TIM_CLK, AgRes::DEG_0_01);
+            sched.initialize(cam, crk);
+            sched.set_speed_crpm(1_000 * CRPM_PER_RPM);
+            sched.start();
+            sched
+        };
+        let (mut on_time, mut late) = (sched(), sched());
+        let crk: Vec<u64> = (0..3).map(|_| on_time.next_crk().dt).collect();
+        let cam: Vec<u64> = (0..2).map(|_| on_time.next_cam(0).dt).collect();
+
+        // Compares programmed 1000 ticks late, the following ones catching up
+        late.delay_crk(1_000);
+        assert_eq!(late.next_crk().dt, crk[0] - 1_000);
+        assert_eq!(late.next_crk().dt, crk[1]);
+        late.delay_cam(0, 1_000);
+        assert_eq!(late.next_cam(0).dt, cam[0] - 1_000);
+        assert_eq!(late.next_cam(0).dt, cam[1]);
+    }
+
+    #[test]
+    fn minimum_speed_keeps_long_compares() {
+        let res = AgRes::DEG_0_01;
+        // Half moon, 360° events of a minute, over 2^32 timer ticks
+        let cam = CamSigGen::new(&CAM_CONFIGS[2], res).unwrap();
+        let trans = simulate(vec![cam], crk_60_2(), SPEED_MIN_CRPM, 250_000_000);
+        let rev = TIM_CLK * 60;
+
+        let edges: Vec<u64> = trans.iter().filter(|tr| tr.ch == Ch::Cam(0)).map(|tr| tr.t).collect();
+        assert_eq!(edges.len(), 4);
+        assert!(edges.windows(2).all(|w| w[1] - w[0] == rev));
+        let crk = rising(&trans, Ch::Crk);
+        assert_eq!(crk[58] - crk[0], rev);
+    }
+
+    #[test]
+    fn inverted_outputs_idle_high() {
+        let (cam, crk) = gens();
+        let mut tim = SimTimer::new(TIM_CLK, AgRes::DEG_0_01);
+        tim.initialize(cam, crk);
+        tim.set_polarity(Polarity::Inverted, 0, Polarity::Normal);
+        assert!(tim.level(Ch::Crk));
+        assert!(!tim.level(Ch::Cam(0)));
+        assert_eq!(tim.transitions(), &[Transition { t: 0, ch: Ch::Crk, level: true }]);
+
+        tim.set_speed_rpm(1_000);
+        tim.start();
+        // One cam cycle, 120 ms
+        tim.run(TIM_CLK * 121 / 1_000);
+        let levels: Vec<bool> = tim.transitions().iter().filter(|tr| tr.ch == Ch::Crk).map(|tr| tr.level).collect();
+        assert!(levels.windows(2).all(|w| w[0] != w[1]));
+        assert_eq!(rising(tim.transitions(), Ch::Cam(0)).len(), 10);
+    }
+
+    #[test]
+    fn polarity_applies_to_one_cam_output() {
+        let (cam, crk) = gens();
+        let mut tim = SimTimer::new(TIM_CLK, AgRes::DEG_0_01);
+        tim.initialize(cam, crk);
+        tim.initialize_cam(1, Some(CamSigGen::new(&CAM_CONFIGS[0], AgRes::DEG_0_01).unwrap()));
+        tim.set_polarity(Polarity::Normal, 1, Polarity::Inverted);
+        assert!(!tim.level(Ch::Crk));
+        assert!(!tim.level(Ch::Cam(0)));
+        assert!(tim.level(Ch::Cam(1)));
+
+        // Idle outputs have no generator to invert
+        tim.set_polarity(Polarity::Normal, 2, Polarity::Inverted);
+        assert!(!tim.level(Ch::Cam(2)));
+        assert!(tim.level(Ch::Cam(1)));
+    }
+
+    /// Rising edges of the cam during 300 ms at 1000 rpm, the cam being offset by `offset` 0.1°
+    fn offset_cam_edges(cam_cfg: usize, offset: i32, dir: Direction) -> Vec<u64> {
+        let mut tim = start_sim(crk_60_2(), &[cam_cfg], 1_000, |tim| {
+            tim.set_cam_offset(0, offset);
+            tim.set_direction(dir);
+        });
+        tim.run(TIM_CLK * 300 / 1_000);
+        assert!(!tim.is_pending());
+        rising(tim.transitions(), Ch::Cam(0))
+    }
+
+    #[test]
+    fn cam_offset_moves_cam_edges() {
+        // 1000 rpm, 10° in 120'000 timer ticks
+        let deg_10 = 120_000;
+        let edges = offset_cam_edges(0, 0, Direction::Forward);
+        let late = offset_cam_edges(0, 100, Direction::Forward);
+        let early = offset_cam_edges(0, -100, Direction::Forward);
+        assert_eq!(late.len(), edges.len());
+        for (t, late) in edges.iter().zip(late.iter()) {
+            assert!((t + deg_10).abs_diff(*late) <= 1);
+        }
+        // Edges moved before the start only set the level the cam starts with
+        assert_eq!(early.len(), edges.len());
+        for (t, early) in edges.iter().zip(early.iter()) {
+            assert!((t - deg_10).abs_diff(*early) <= 1);
+        }
+    }
+
+    #[test]
+    fn cam_offset_is_kept_when_turning_around() {
+        // 1000 rpm, 1° in 12'000 timer ticks, turning around on the reference at 720°
+        let deg = 12_000;
+        let cycle = 720 * deg;
+        // Single tooth from 60° to 80°, from 90° to 110° when delayed by 30°
+        let edges = offset_cam_edges(1, 300, Direction::Forward);
+        assert_eq!(edges[..2], [90 * deg, cycle + 90 * deg]);
+
+        // Crank turning around in 3°, the cam crossing 110° backwards 610° later
+        let edges = offset_cam_edges(1, 300, Direction::Reverse);
+        assert_eq!(edges.len(), 2);
+        assert_eq!(edges[0], 90 * deg);
+        assert!(edges[1].abs_diff(cycle + 613 * deg) <= 1);
+    }
+
+    #[test]
+    fn cam_offset_jumps_keep_compares_apart() {
+        let dt_min = TIM_CLK * OC_DT_MIN_US / 1_000_000;
+        // Cam edges after 80 ms, two 40 ms cycles at 3000 rpm, delayed by 30° until `jump_us` 
+        // and advanced by 30° after
+        let edges = |jump_us: u64| {
+            let mut tim = start_sim(crk_60_2(), &[0], 3_000, |tim| tim.set_cam_offset(0, 300));
+            tim.run(TIM_CLK * jump_us / 1_000_000);
+            tim.set_cam_offset(0, -300);
+            tim.run(TIM_CLK * (120_000 - jump_us) / 1_000_000);
+            let trans: Vec<Transition> = tim.take_transitions().into_iter().filter(|tr| tr.ch == Ch::Cam(0)).collect();
+            // Events moved before the last compare only set the level they end on, as soon as possible
+            assert!(trans.windows(2).all(|w| w[1].t - w[0].t >= dt_min), "jump at {} µs", jump_us);
+            trans.into_iter().filter(|tr| tr.t >= TIM_CLK * 80 / 1_000).collect::<Vec<_>>()
+        };
+        // Back in phase from the next cycle, wherever the jump happens
+        let early = edges(0);
+        assert!(!early.is_empty());
+        for jump_us in (1_000..40_000).step_by(250) {
+            assert_eq!(edges(jump_us), early, "jump at {} µs", jump_us);
+        }
+    }
+
+    #[test]
+    fn changes_wait_for_the_offset_cam() {
+        let mut tim = start_sim(crk_60_2(), &[0], 1_000, |tim| tim.set_cam_offset(0, -1_500));
+        let cam = CamSigGen::new(&CAM_CONFIGS[1], AgRes::DEG_0_01).unwrap();
+        tim.set_sig_gen(cam, crk_60_2());
+        // Applied on the reference at 720°, the cam tooth then rising at 60° - 150° without 
+        // generating the edges moved before the reference
+        tim.run(TIM_CLK * 300 / 1_000);
+        assert!(!tim.is_pending());
+        let deg = 12_000;
+        let edges = rising(tim.transitions(), Ch::Cam(0));
+        let swapped: Vec<u64> = edges.into_iter().filter(|&t| t > 720 * deg).collect();
+        assert_eq!(swapped.len(), 1);
+        assert!(swapped[0].abs_diff(1_350 * deg) <= 1);
+    }
+
+    /// Widths of the crank pulses of `crk_cfg` in `dir` at `rpm` and low times between them, 
+    /// in timer ticks, 45 µs forward and 90 µs reverse
+    fn pulse_widths(crk_cfg: &'static CrkCfg, rpm: u32, dir: Direction) -> (Vec<u64>, Vec<u64>) {
+        let mut crk = CrkSigGen::new(crk_cfg, AgRes::DEG_0_01).unwrap();
+        crk.set_mode(CrkMode::Pulse { fwd_us: 45, rev_us: 90 });
+        crk.set_direction(dir);
+        let mut tim = start_sim(crk, &[0], rpm, |_| ());
+        // One revolution
+        tim.run(TIM_CLK * 60 / rpm as u64);
+        let trans: Vec<&Transition> = tim.transitions().iter().filter(|tr| tr.ch == Ch::Crk).collect();
+        let times = |level: bool| trans.windows(2).filter(|w| w[0].level == level).map(|w| w[1].t - w[0].t).collect();
+        (times(true), times(false))
+    }
+
+    #[test]
+    fn pulse_widths_dont_depend_on_speed() {
+        // 72 timer ticks per µs
+        let (fwd, rev) = (45 * 72, 90 * 72);
+        for rpm in [1_000, 6_000] {
+            let (widths, _) = pulse_widths(&CRK_CONFIGS[2], rpm, Direction::Forward);
+            assert!(widths.len() > 50 && widths.iter().all(|&w| w == fwd), "{} rpm", rpm);
+            let (widths, _) = pulse_widths(&CRK_CONFIGS[2], rpm, Direction::Reverse);
+            assert!(widths.len() > 50 && widths.iter().all(|&w| w == rev), "{} rpm", rpm);
+        }
+    }
+
+    #[test]
+    fn pulses_leave_the_output_low_before_the_next_tooth() {
+        let fwd = 45 * 72;
+        let low_min = TIM_CLK * OC_DT_MIN_US / 1_000_000;
+        // At 12000 rpm, a tooth lasts 83.3 µs on a 60-2 wheel: reverse pulses end the 
+        // minimal compare distance before the next one
+        let pitch = TIM_CLK * 60 / 12_000 / 60;
+        let (widths, lows) = pulse_widths(&CRK_CONFIGS[2], 12_000, Direction::Forward);
+        assert!(widths.len() > 50 && widths.iter().all(|&w| w == fwd));
+        assert!(lows.iter().all(|&low| low >= pitch - fwd));
+        let (widths, lows) = pulse_widths(&CRK_CONFIGS[2], 12_000, Direction::Reverse);
+        assert!(widths.len() > 50 && widths.iter().all(|&w| w == pitch - low_min));
+        assert!(lows.iter().all(|&low| low >= low_min));
+
+        // A tooth lasts 41.7 µs on a 120-2 wheel, even forward pulses don't fit
+        let pitch = TIM_CLK * 60 / 12_000 / 120;
+        let (widths, lows) = pulse_widths(&CRK_CONFIGS[0], 12_000, Direction::Forward);
+        assert!(widths.len() > 100 && widths.iter().all(|&w| w == pitch - low_min));
+        assert!(lows.iter().all(|&low| low >= low_min));
+    }
+
+    /// Rising edges of cam outputs generating `(cam cfg, offset)`, in order, during 300 ms at 1000 rpm
+    fn multi_cam_edges(cams: &[(usize, i32)], dir: Direction) -> Vec<Vec<u64>> {
+        let cfgs: Vec<usize> = cams.iter().map(|&(cam_cfg, _)| cam_cfg).collect();
+        let mut tim = start_sim(crk_60_2(), &cfgs, 1_000, |tim| {
+            for (idx, &(_, offset)) in cams.iter().enumerate() {
+                tim.set_cam_offset(idx, offset);
+            }
+            tim.set_direction(dir);
+        });
+        tim.run(TIM_CLK * 300 / 1_000);
+        assert!(!tim.is_pending());
+        (0..CAM_NR_MAX).map(|idx| rising(tim.transitions(), Ch::Cam(idx))).collect()
+    }
+
+    #[test]
+    fn cam_outputs_have_their_own_pattern_and_phase() {
+        for dir in [Direction::Forward, Direction::Reverse] {
+            let cams = [(0, 0), (1, 300), (1, -300), (4, 100)];
+            let edges = multi_cam_edges(&cams, dir);
+            for (idx, &(cam_cfg, offset)) in cams.iter().enumerate() {
+                assert!(!edges[idx].is_empty());
+                assert_eq!(edges[idx], offset_cam_edges(cam_cfg, offset, dir), "cam {} {:?}", idx, dir);
+            }
+        }
+        // Outputs without generator stay idle
+        let edges = multi_cam_edges(&[(0, 0), (1, 0)], Direction::Forward);
+        assert!(edges[2].is_empty() && edges[3].is_empty());
+    }
+
+    #[test]
+    fn cam_outputs_are_switched_on_the_reference() {
+        let mut tim = start_sim(crk_60_2(), &[0], 1_000, |_| ());
+        let cam = CamSigGen::new(&CAM_CONFIGS[1], AgRes::DEG_0_01).unwrap();
+        tim.set_cam_sig_gen(1, Some(cam));
+        tim.run(TIM_CLK * 300 / 1_000);
+        assert!(!tim.is_pending());
+        // Disabled on the reference at 1440°, 360 ms
+        tim.set_cam_sig_gen(1, None);
+        tim.run(TIM_CLK * 240 / 1_000);
+        assert!(!tim.is_pending());
+
+        // Enabled on the reference at 720°, the tooth rising at 60° in the following cycles
+        let deg = 12_000;
+        assert_eq!(rising(tim.transitions(), Ch::Cam(1)), [780 * deg, 1_500 * deg]);
+        assert!(!tim.level(Ch::Cam(1)));
+    }
+
+    /// Rising edges of the crank and cam during `dur_ms` at 1000 rpm, with the cam phase 
+    /// variation `(target, rate, osc_amp, osc_period_ms)` if any
+    fn vvt_edges(cam_cfg: usize, vvt: Option<(i32, u32, u32, u32)>, dur_ms: u64) -> (Vec<u64>, Vec<u64>) {
+        let mut tim = start_sim(crk_60_2(), &[cam_cfg], 1_000, |tim| {
+            if let Some((target, rate, osc_amp, osc_period_ms)) = vvt {
+                tim.set_cam_vvt(0, target, rate, osc_amp, osc_period_ms);
+            }
+        });
+        tim.run(TIM_CLK * dur_ms / 1_000);
+        (rising(tim.transitions(), Ch::Crk), rising(tim.transitions(), Ch::Cam(0)))
+    }
+
+    #[test]
+    fn vvt_slews_cam_to_target() {
+        // 1000 rpm, 1° in 12'000 timer ticks, a 720° cycle in 120 ms
+        let deg = 12_000;
+        let (crk, _) = vvt_edges(1, None, 1_000);
+        let (vvt_crk, edges) = vvt_edges(1, Some((300, 100, 0, 0)), 1_000);
+        assert_eq!(vvt_crk, crk);
+
+        // Single tooth rising at 60°, the first edge being scheduled before any variation, 
+        // then delayed by about 12° per 120 ms cycle at 100°/s
+        let delays: Vec<u64> = edges.iter().enumerate().map(|(k, t)| t - (k as u64 * 720 + 60) * deg).collect();
+        assert_eq!(delays.len(), 9);
+        assert_eq!(delays[0], 0);
+        for w in delays.windows(2) {
+            assert!(w[0] <= w[1] && w[1] - w[0] <= 13 * deg);
+        }
+        assert!(delays[3..].iter().all(|d| d.abs_diff(30 * deg) <= 1));
+    }
+
+    #[test]
+    fn vvt_oscillates_cam_around_target() {
+        let deg = 12_000;
+        let (crk, edges) = vvt_edges(0, None, 600);
+        let (vvt_crk, vvt_edges) = vvt_edges(0, Some((0, 1_000, 50, 200)), 600);
+        assert_eq!(vvt_crk, crk);
+        assert_eq!(vvt_edges.len(), edges.len());
+
+        // Within 5° of the edges without offset, both ways
+        let delays: Vec<i64> = edges.iter().zip(vvt_edges.iter()).map(|(t, vvt)| *vvt as i64 - *t as i64).collect();
+        assert!(delays.iter().all(|d| d.abs() <= 5 * deg + 1));
+        assert!(delays.iter().any(|&d| d > 3 * deg));
+        assert!(delays.iter().any(|&d| d < -3 * deg));
+    }
+
+    /// Positions of the rising edges of cam output `idx` in crank transitions since the start, 
+    /// with the fraction of the crank interval they're in. The speed is constant between 
+    /// crank transitions, the cam phase to the crank being exact.
+    fn crank_phases(trans: &[Transition], idx: usize) -> Vec<f64> {
+        let crk: Vec<u64> = trans.iter().filter(|tr| tr.ch == Ch::Crk).map(|tr| tr.t).collect();
+        let phase = |t: u64| {
+            let n = crk.partition_point(|&c| c <= t).checked_sub(1)?;
+            let next = *crk.get(n + 1)?;
+            Some(n as f64 + (t - crk[n]) as f64 / (next - crk[n]) as f64)
+        };
+        rising(trans, Ch::Cam(idx)).into_iter().map_while(phase).collect()
+    }
+
+    /// Generation run for a duration, ms, then changed by an action
+    type Step<'a> = (u64, &'a dyn Fn(&mut SimTimer));
+
+    /// Cam phases of `CAM_CONFIGS[0]` and `CAM_CONFIGS[1]` on outputs 0 and 1, starting at 
+    /// `rpm` and running `steps` in order
+    fn speed_change_phases(rpm: u32, steps: &[Step]) -> [Vec<f64>; 2] {
+        let mut tim = start_sim(crk_60_2(), &[0, 1], rpm, |_| ());
+        for (dur_ms, action) in steps {
+            tim.run(TIM_CLK * dur_ms / 1_000);
+            action(&mut tim);
+        }
+        [0, 1].map(|idx| crank_phases(tim.transitions(), idx))
+    }
+
+    /// Check the cam phases of a run against a constant speed
+    fn assert_same_phases(phases: &[Vec<f64>; 2], what: &str) {
+        // 600 cycles
+        let reference = speed_change_phases(6_000, &[(12_000, &|_| ())]);
+        for (phases, reference) in phases.iter().zip(reference.iter()) {
+            assert!(phases.len() >= 3, "{}", what);
+            for (k, (phase, reference)) in phases.iter().zip(reference.iter()).enumerate() {
+                assert!((phase - reference).abs() < 1e-3, "{}, edge {}: {} instead of {}", what, k, phase, reference);
+            }
+        }
+    }
+
+    #[test]
+    fn speed_changes_keep_the_cam_phase() {
+        for (from, to, at_ms, dur_ms) in [(100, 6_000, 500, 100), (6_000, 100, 7, 2_500), (1_000, 3_000, 50, 200)] {
+            let phases = speed_change_phases(from, &[(at_ms, &|tim| tim.set_speed_rpm(to)), (dur_ms, &|_| ())]);
+            assert_same_phases(&phases, &format!("{} to {} rpm at {} ms", from, to, at_ms));
+        }
+    }
+
+    #[test]
+    fn speed_ramps_keep_the_cam_phase() {
+        for (from, to, rate) in [(1_000, 6_000, 5_000), (6_000, 500, 20_000)] {
+            let phases = speed_change_phases(from, &[(20, &|tim| tim.set_speed_ramp(to * CRPM_PER_RPM, rate)), (1_200, &|_| ())]);
+            assert_same_phases(&phases, &format!("{} to {} rpm at {} rpm/s", from, to, rate));
+        }
+    }
+
+    #[test]
+    fn speed_profiles_keep_the_cam_phase() {
+        // Revving from idle to 6000 rpm and back
+        let play = |tim: &mut SimTimer| tim.set_profile(Some(ProfilePlayer::new(&PROFILES[1], false, TIM_CLK)));
+        let phases = speed_change_phases(1_000, &[(50, &play), (10_000, &|_| ())]);
+        assert_same_phases(&phases, "profile");
+    }
+}
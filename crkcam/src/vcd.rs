@@ -0,0 +1,100 @@
+//! Value Change Dump export of simulated signals, to look at them in a
+//! waveform viewer such as GTKWave or PulseView.
+use super::sim::{Ch, Transition};
+
+use std::io::{Result, Write};
+use std::vec::Vec;
+
+/// Time unit of the dump, in ns
+const TIMESCALE_NS: u64 = 1;
+
+/// Identifier of the variable of an output in the dump, cams taking letters to stay
+/// clear of the `#` and `$` of times and keywords
+fn var_id(ch: Ch) -> char {
+    match ch {
+        Ch::Crk => '!',
+        Ch::Cam(idx) => (b'a' + idx as u8) as char,
+    }
+}
+
+/// Write the transitions of the crank and cam outputs as a VCD. Times are in
+/// timer ticks at `tick_freq` Hz, outputs being low before their first transition
+/// as the ones of the simulated timer. Cam outputs are dumped up to the last one 
+/// changing, the first one being always dumped.
+pub fn write_vcd<W: Write>(w: &mut W, trans: &[Transition], tick_freq: u64) -> Result<()> {
+    let cam_nr = trans.iter().filter_map(|tr| match tr.ch {
+        Ch::Cam(idx) => Some(idx + 1),
+        Ch::Crk => None,
+    }).max().unwrap_or(1);
+    let chs: Vec<Ch> = core::iter::once(Ch::Crk).chain((0..cam_nr).map(Ch::Cam)).collect();
+
+    writeln!(w, "$version ccgen $end")?;
+    writeln!(w, "$timescale {}ns $end", TIMESCALE_NS)?;
+    writeln!(w, "$scope module ccgen $end")?;
+    for &ch in chs.iter() {
+        match ch {
+            Ch::Crk => writeln!(w, "$var wire 1 {} crk $end", var_id(ch))?,
+            Ch::Cam(idx) => writeln!(w, "$var wire 1 {} cam{} $end", var_id(ch), idx)?,
+        }
+    }
+    writeln!(w, "$upscope $end")?;
+    writeln!(w, "$enddefinitions $end")?;
+    writeln!(w, "#0")?;
+    writeln!(w, "$dumpvars")?;
+    for &ch in chs.iter() {
+        writeln!(w, "0{}", var_id(ch))?;
+    }
+    writeln!(w, "$end")?;
+
+    let mut last_t = 0;
+    for tr in trans {
+        // Rounded to the nearest time unit, transitions at the same time are grouped
+        let t = (tr.t as u128 * 1_000_000_000 / TIMESCALE_NS as u128 + tick_freq as u128 / 2) / tick_freq as u128;
+        let t = t as u64;
+        if t != last_t {
+            writeln!(w, "#{}", t)?;
+            last_t = t;
+        }
+        writeln!(w, "{}{}", tr.level as u8, var_id(tr.ch))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transitions_are_time_scaled() {
+        let trans = [
+            Transition { t: 0, ch: Ch::Crk, level: true },
+            Transition { t: 72, ch: Ch::Crk, level: false },
+            Transition { t: 72, ch: Ch::Cam(0), level: true },
+            Transition { t: 100, ch: Ch::Cam(0), level: false },
+        ];
+        let mut out = std::vec::Vec::new();
+        write_vcd(&mut out, &trans, 72_000_000).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        let changes: std::vec::Vec<&str> = out.lines().skip_while(|l| *l != "$end").skip(1).collect();
+        assert_eq!(changes, ["1!", "#1000", "0!", "1a", "#1389", "0a"]);
+    }
+
+    #[test]
+    fn cam_outputs_are_dumped_up_to_the_last_one() {
+        let trans = [Transition { t: 72, ch: Ch::Cam(3), level: true }];
+        let mut out = std::vec::Vec::new();
+        write_vcd(&mut out, &trans, 72_000_000).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        let vars: std::vec::Vec<&str> = out.lines().filter(|l| l.starts_with("$var")).collect();
+        assert_eq!(vars, [
+            "$var wire 1 ! crk $end",
+            "$var wire 1 a cam0 $end",
+            "$var wire 1 b cam1 $end",
+            "$var wire 1 c cam2 $end",
+            "$var wire 1 d cam3 $end",
+        ]);
+        assert!(out.ends_with("#1000\n1d\n"));
+    }
+}
@@ -0,0 +1,442 @@
+use heapless::consts::U32;
+use heapless::Vec;
+
+use super::{read_data, send_data};
+use crkcam::cmn::{Direction, Polarity};
+use crkcam::crk::CrkMode;
+use crkcam::pattern::Pattern;
+use crkcam::sched::CAM_NR_MAX;
+
+/// Command identifier: select crank and cam configurations
+///
+/// Request: `[CMD_SET_CFG, crk_id, cam_id]`
+/// Response: `[CMD_SET_CFG, crk_id, cam_id]` with the now active ids
+pub const CMD_SET_CFG: u8 = 0x01;
+
+/// Command identifier: set crank and cam output polarities, 0: normal, 1: inverted
+///
+/// Request: `[CMD_SET_POL, cam, crk_pol, cam_pol]`
+/// Response: `[CMD_SET_POL, cam, crk_pol, cam_pol]`
+pub const CMD_SET_POL: u8 = 0x02;
+
+/// Command identifier: set rotation direction, 0: forward, 1: reverse
+///
+/// Request: `[CMD_SET_DIR, dir]`
+/// Response: `[CMD_SET_DIR, dir]` once the direction is applied
+pub const CMD_SET_DIR: u8 = 0x03;
+
+/// Command identifier: set crank output mode, 0: tooth, 1: pulse with direction 
+/// encoded in the pulse width, widths in µs as big endian
+///
+/// Request: `[CMD_SET_CRK_MODE, mode, fwd_us_h, fwd_us_l, rev_us_h, rev_us_l]`
+/// Response: same as request, once the mode is applied
+pub const CMD_SET_CRK_MODE: u8 = 0x04;
+
+/// Command identifier: set generation speed in 0.01 rpm, big endian
+///
+/// Request: `[CMD_SET_SPEED, crpm (4 bytes)]`
+/// Response: `[CMD_SET_SPEED, requested crpm (4 bytes), set crpm (4 bytes), degree tick ppm (2 bytes)]`, 
+/// the set speed being the requested one limited to the speed range and the degree tick one 
+/// timer tick relative to the period of one degree
+pub const CMD_SET_SPEED: u8 = 0x05;
+
+/// Command identifier: ramp speed to a target in 0.01 rpm at a rate in rpm/s, big endian
+///
+/// Request: `[CMD_SET_RAMP, target crpm (4 bytes), rate (2 bytes)]`
+/// Response: same as request
+pub const CMD_SET_RAMP: u8 = 0x06;
+
+/// Command identifier: control speed profile playback, action 0: stop, 
+/// 1: play profile `id` from its start, looping if `loop` is 1, 2: pause, 3: resume
+///
+/// Request: `[CMD_PROFILE, action, id, loop]`
+/// Response: `[CMD_PROFILE, action, id, loop]`, `id` and `loop` being 0 unless playing
+pub const CMD_PROFILE: u8 = 0x07;
+
+/// Command identifier: list the trigger patterns, name zero padded to `Pattern::NAME_LEN_MAX` bytes
+///
+/// Request: `[CMD_LIST_PATTERN, idx]`
+/// Response: `[CMD_LIST_PATTERN, idx, pattern_nr, crk_id, cam_id, name (16 bytes)]`
+pub const CMD_LIST_PATTERN: u8 = 0x08;
+
+/// Command identifier: select the crank and cam configurations of a trigger pattern by name, 
+/// zero padded to `Pattern::NAME_LEN_MAX` bytes
+///
+/// Request: `[CMD_SELECT_PATTERN, name (16 bytes)]`
+/// Response: `[CMD_SELECT_PATTERN, idx, crk_id, cam_id]` once the pattern is active
+pub const CMD_SELECT_PATTERN: u8 = 0x09;
+
+/// Command identifier: set the phase offset of cam output `cam` to the crank in 0.1°, 
+/// signed big endian, a positive offset delaying the cam
+///
+/// Request: `[CMD_SET_CAM_OFFSET, cam, offset (2 bytes)]`
+/// Response: same as request
+pub const CMD_SET_CAM_OFFSET: u8 = 0x0A;
+
+/// Command identifier: move the phase offset of cam output `cam` towards a target in 0.1°, 
+/// signed, at a rate in °/s, the target oscillating by an amplitude in 0.1° over a period 
+/// in ms if both are set, big endian. A rate of 0 sets the offset at once.
+///
+/// Request: `[CMD_SET_CAM_VVT, cam, target (2 bytes), rate (2 bytes), osc_amp (2 bytes), osc_period_ms (2 bytes)]`
+/// Response: same as request
+pub const CMD_SET_CAM_VVT: u8 = 0x0B;
+
+/// Command identifier: select the configuration of cam output `cam`, `CAM_ID_NONE` 
+/// disabling the output
+///
+/// Request: `[CMD_SELECT_CAM, cam, cam_id]`
+/// Response: `[CMD_SELECT_CAM, cam, cam_id]` once the configuration is active
+pub const CMD_SELECT_CAM: u8 = 0x0C;
+
+/// Configuration id disabling a cam output
+pub const CAM_ID_NONE: u8 = 0xFF;
+
+/// Cam phase offset range, 0.1°
+pub const CAM_OFFSET_MAX: i16 = 3_600;
+
+/// Response identifier reporting a command error
+///
+/// Response: `[RSP_ERR, cmd_id, err_code]`
+pub const RSP_ERR: u8 = 0xFF;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Cmd {
+    /// Select the active crank and cam configurations by index
+    SetCfg { crk_id: u8, cam_id: u8 },
+    /// Override the polarities of the crank and of a cam output of the active configurations
+    SetPol { cam: u8, crk_pol: Polarity, cam_pol: Polarity },
+    /// Set the rotation direction of both crank and cam
+    SetDir { dir: Direction },
+    /// Set the output mode of the crank signal
+    SetCrkMode { mode: CrkMode },
+    /// Set the generation speed, 0.01 rpm
+    SetSpeed { crpm: u32 },
+    /// Ramp the generation speed to a target, 0.01 rpm, at a given rate, rpm/s
+    SetRamp { crpm: u32, rate: u16 },
+    /// Control the playback of speed profiles
+    Profile { action: ProfileAction },
+    /// Describe the trigger pattern of index `idx`
+    ListPattern { idx: u8 },
+    /// Select the crank and cam configurations of a trigger pattern by name
+    SelectPattern { name: [u8; Pattern::NAME_LEN_MAX] },
+    /// Set the phase offset of a cam output to the crank, 0.1°
+    SetCamOffset { cam: u8, offset: i16 },
+    /// Move the phase offset of a cam output towards a target, 0.1°, at a given rate, °/s, 
+    /// the target oscillating by an amplitude, 0.1°, over a period, ms
+    SetCamVvt { cam: u8, target: i16, rate: u16, osc_amp: u16, osc_period_ms: u16 },
+    /// Select the configuration of a cam output by index, `CAM_ID_NONE` disabling it
+    SelectCam { cam: u8, cam_id: u8 },
+}
+
+/// Speed profile playback control
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ProfileAction {
+    Stop,
+    Play { id: u8, looped: bool },
+    Pause,
+    Resume,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CmdErr {
+    /// Not enough bytes received yet to decode the command
+    Incomplete,
+    /// Unknown command identifier
+    Unknown,
+    /// Command decoded, but one of its arguments is out of range
+    InvalidArg,
+    /// Too many commands waiting to be applied
+    Busy,
+}
+
+impl CmdErr {
+    fn code(self) -> u8 {
+        match self {
+            CmdErr::Incomplete => 0x01,
+            CmdErr::Unknown => 0x02,
+            CmdErr::InvalidArg => 0x03,
+            CmdErr::Busy => 0x04,
+        }
+    }
+}
+
+impl Cmd {
+    /// Decode a command from the start of a frame
+    ///
+    /// **Return value**
+    ///
+    /// * Ok((Cmd, usize)): decoded command and number of bytes it used
+    /// * Err(CmdErr): frame couldn't be decoded
+    pub fn parse(frm: &[u8]) -> Result<(Cmd, usize), CmdErr> {
+        let (id, arg) = match frm.split_first() {
+            Some((id, arg)) => (*id, arg),
+            None => return Err(CmdErr::Incomplete),
+        };
+        let len = arg_len(id).ok_or(CmdErr::Unknown)?;
+        if arg.len() < len {
+            return Err(CmdErr::Incomplete);
+        }
+        let cmd = match id {
+            CMD_SET_CFG => Cmd::SetCfg { crk_id: arg[0], cam_id: arg[1] },
+            CMD_SET_POL => Cmd::SetPol {
+                cam: parse_cam(arg[0])?,
+                crk_pol: parse_pol(arg[1])?,
+                cam_pol: parse_pol(arg[2])?,
+            },
+            CMD_SET_DIR => Cmd::SetDir { dir: parse_dir(arg[0])? },
+            CMD_SET_CRK_MODE => Cmd::SetCrkMode { mode: parse_crk_mode(&arg[..5])? },
+            CMD_SET_SPEED => Cmd::SetSpeed { crpm: u32::from_be_bytes([arg[0], arg[1], arg[2], arg[3]]) },
+            CMD_SET_RAMP => Cmd::SetRamp {
+                crpm: u32::from_be_bytes([arg[0], arg[1], arg[2], arg[3]]),
+                rate: u16::from_be_bytes([arg[4], arg[5]]),
+            },
+            CMD_PROFILE => Cmd::Profile { action: parse_profile_action(&arg[..3])? },
+            CMD_LIST_PATTERN => Cmd::ListPattern { idx: arg[0] },
+            CMD_SELECT_PATTERN => {
+                let mut name = [0; Pattern::NAME_LEN_MAX];
+                name.copy_from_slice(&arg[..Pattern::NAME_LEN_MAX]);
+                Cmd::SelectPattern { name }
+            }
+            CMD_SET_CAM_OFFSET => Cmd::SetCamOffset {
+                cam: parse_cam(arg[0])?,
+                offset: parse_cam_offset(&arg[1..3])?,
+            },
+            CMD_SET_CAM_VVT => parse_cam_vvt(&arg[..9])?,
+            CMD_SELECT_CAM => Cmd::SelectCam { cam: parse_cam(arg[0])?, cam_id: arg[1] },
+            _ => return Err(CmdErr::Unknown),
+        };
+        Ok((cmd, len + 1))
+    }
+
+    /// Identifier of the command, used to tag its response
+    pub fn id(&self) -> u8 {
+        match self {
+            Cmd::SetCfg { .. } => CMD_SET_CFG,
+            Cmd::SetPol { .. } => CMD_SET_POL,
+            Cmd::SetDir { .. } => CMD_SET_DIR,
+            Cmd::SetCrkMode { .. } => CMD_SET_CRK_MODE,
+            Cmd::SetSpeed { .. } => CMD_SET_SPEED,
+            Cmd::SetRamp { .. } => CMD_SET_RAMP,
+            Cmd::Profile { .. } => CMD_PROFILE,
+            Cmd::ListPattern { .. } => CMD_LIST_PATTERN,
+            Cmd::SelectPattern { .. } => CMD_SELECT_PATTERN,
+            Cmd::SetCamOffset { .. } => CMD_SET_CAM_OFFSET,
+            Cmd::SetCamVvt { .. } => CMD_SET_CAM_VVT,
+            Cmd::SelectCam { .. } => CMD_SELECT_CAM,
+        }
+    }
+}
+
+/// Number of argument bytes following the identifier of a command
+fn arg_len(id: u8) -> Option<usize> {
+    match id {
+        CMD_SET_CFG => Some(2),
+        CMD_SET_POL => Some(3),
+        CMD_SET_DIR => Some(1),
+        CMD_SET_CRK_MODE => Some(5),
+        CMD_SET_SPEED => Some(4),
+        CMD_SET_RAMP => Some(6),
+        CMD_PROFILE => Some(3),
+        CMD_LIST_PATTERN => Some(1),
+        CMD_SELECT_PATTERN => Some(Pattern::NAME_LEN_MAX),
+        CMD_SET_CAM_OFFSET => Some(3),
+        CMD_SET_CAM_VVT => Some(9),
+        CMD_SELECT_CAM => Some(2),
+        _ => None,
+    }
+}
+
+fn parse_pol(val: u8) -> Result<Polarity, CmdErr> {
+    match val {
+        0 => Ok(Polarity::Normal),
+        1 => Ok(Polarity::Inverted),
+        _ => Err(CmdErr::InvalidArg),
+    }
+}
+
+fn parse_dir(val: u8) -> Result<Direction, CmdErr> {
+    match val {
+        0 => Ok(Direction::Forward),
+        1 => Ok(Direction::Reverse),
+        _ => Err(CmdErr::InvalidArg),
+    }
+}
+
+/// Encode a direction as in the direction command
+pub fn dir_to_u8(dir: Direction) -> u8 {
+    match dir {
+        Direction::Forward => 0,
+        Direction::Reverse => 1,
+    }
+}
+
+fn parse_crk_mode(arg: &[u8]) -> Result<CrkMode, CmdErr> {
+    let fwd_us = u16::from_be_bytes([arg[1], arg[2]]);
+    let rev_us = u16::from_be_bytes([arg[3], arg[4]]);
+    match arg[0] {
+        0 => Ok(CrkMode::Tooth),
+        1 if fwd_us > 0 && rev_us > 0 => Ok(CrkMode::Pulse { fwd_us, rev_us }),
+        _ => Err(CmdErr::InvalidArg),
+    }
+}
+
+/// Encode a crank mode as in the crank mode command
+pub fn crk_mode_to_bytes(mode: CrkMode) -> [u8; 5] {
+    match mode {
+        CrkMode::Tooth => [0; 5],
+        CrkMode::Pulse { fwd_us, rev_us } => {
+            let fwd = fwd_us.to_be_bytes();
+            let rev = rev_us.to_be_bytes();
+            [1, fwd[0], fwd[1], rev[0], rev[1]]
+        }
+    }
+}
+
+fn parse_profile_action(arg: &[u8]) -> Result<ProfileAction, CmdErr> {
+    match (arg[0], arg[2]) {
+        (0, _) => Ok(ProfileAction::Stop),
+        (1, 0) => Ok(ProfileAction::Play { id: arg[1], looped: false }),
+        (1, 1) => Ok(ProfileAction::Play { id: arg[1], looped: true }),
+        (2, _) => Ok(ProfileAction::Pause),
+        (3, _) => Ok(ProfileAction::Resume),
+        _ => Err(CmdErr::InvalidArg),
+    }
+}
+
+fn parse_cam(val: u8) -> Result<u8, CmdErr> {
+    if val as usize >= CAM_NR_MAX {
+        return Err(CmdErr::InvalidArg);
+    }
+    Ok(val)
+}
+
+fn parse_cam_offset(arg: &[u8]) -> Result<i16, CmdErr> {
+    let offset = i16::from_be_bytes([arg[0], arg[1]]);
+    if offset.abs() > CAM_OFFSET_MAX {
+        return Err(CmdErr::InvalidArg);
+    }
+    Ok(offset)
+}
+
+/// Parse a phase variation, the oscillation staying in the range of the cam phase offset
+fn parse_cam_vvt(arg: &[u8]) -> Result<Cmd, CmdErr> {
+    let cam = parse_cam(arg[0])?;
+    let target = parse_cam_offset(&arg[1..3])?;
+    let osc_amp = u16::from_be_bytes([arg[5], arg[6]]);
+    if target.unsigned_abs() as u32 + osc_amp as u32 > CAM_OFFSET_MAX as u32 {
+        return Err(CmdErr::InvalidArg);
+    }
+    Ok(Cmd::SetCamVvt {
+        cam,
+        target,
+        rate: u16::from_be_bytes([arg[3], arg[4]]),
+        osc_amp,
+        osc_period_ms: u16::from_be_bytes([arg[7], arg[8]]),
+    })
+}
+
+fn pol_to_u8(pol: Polarity) -> u8 {
+    match pol {
+        Polarity::Normal => 0,
+        Polarity::Inverted => 1,
+    }
+}
+
+/// Send the response of the polarity command
+pub fn send_pol(cam: u8, crk_pol: Polarity, cam_pol: Polarity) -> Result<(), ()> {
+    send_data(&[CMD_SET_POL, cam, pol_to_u8(crk_pol), pol_to_u8(cam_pol)])
+}
+
+/// Send the response of the speed command
+pub fn send_speed(req_crpm: u32, crpm: u32, deg_tick_ppm: u32) -> Result<(), ()> {
+    let req = req_crpm.to_be_bytes();
+    let set = crpm.to_be_bytes();
+    let tick = (deg_tick_ppm.min(u16::MAX as u32) as u16).to_be_bytes();
+    send_data(&[
+        CMD_SET_SPEED,
+        req[0], req[1], req[2], req[3],
+        set[0], set[1], set[2], set[3],
+        tick[0], tick[1],
+    ])
+}
+
+/// Send the response of the ramp command
+pub fn send_ramp(crpm: u32, rate: u16) -> Result<(), ()> {
+    let spd = crpm.to_be_bytes();
+    let rate = rate.to_be_bytes();
+    send_data(&[CMD_SET_RAMP, spd[0], spd[1], spd[2], spd[3], rate[0], rate[1]])
+}
+
+/// Send the response of the cam phase variation command
+pub fn send_cam_vvt(cam: u8, target: i16, rate: u16, osc_amp: u16, osc_period_ms: u16) -> Result<(), ()> {
+    let target = target.to_be_bytes();
+    let rate = rate.to_be_bytes();
+    let amp = osc_amp.to_be_bytes();
+    let period = osc_period_ms.to_be_bytes();
+    send_data(&[CMD_SET_CAM_VVT, cam, target[0], target[1], rate[0], rate[1], amp[0], amp[1], period[0], period[1]])
+}
+
+/// Send the response of the pattern list command
+pub fn send_pattern(idx: u8, pattern_nr: u8, pat: &Pattern) -> Result<(), ()> {
+    let mut rsp = [0; 5 + Pattern::NAME_LEN_MAX];
+    rsp[..5].copy_from_slice(&[CMD_LIST_PATTERN, idx, pattern_nr, pat.crk_id, pat.cam_id]);
+    rsp[5..5 + pat.name.len()].copy_from_slice(pat.name.as_bytes());
+    send_data(&rsp)
+}
+
+/// Send an error response for the command with identifier `id`
+pub fn send_err(id: u8, err: CmdErr) -> Result<(), ()> {
+    send_data(&[RSP_ERR, id, err.code()])
+}
+
+/// Accumulate received bytes until a full command frame is available
+pub struct CmdReader {
+    frm: Vec<u8, U32>,
+}
+
+impl CmdReader {
+    pub const fn new() -> CmdReader {
+        CmdReader {
+            frm: Vec(heapless::i::Vec::new()),
+        }
+    }
+
+    /// Poll the UART reception buffer for a new command
+    ///
+    /// **Return value**
+    ///
+    /// * Some(Ok(Cmd)): a complete command has been received
+    /// * Some(Err((u8, CmdErr))): identifier of the rejected frame and reason,
+    ///   the frame has been dropped
+    /// * None: no complete command received yet
+    pub fn poll(&mut self) -> Option<Result<Cmd, (u8, CmdErr)>> {
+        let mut buf = [0; 32];
+        let free = self.frm.capacity() - self.frm.len();
+        if let Ok(len) = read_data(&mut buf[..free]) {
+            // Cannot fail, reading is limited to the free space of the frame
+            self.frm.extend_from_slice(&buf[..len]).unwrap();
+        }
+
+        match Cmd::parse(&self.frm) {
+            Ok((cmd, len)) => {
+                self.consume(len);
+                Some(Ok(cmd))
+            }
+            Err(CmdErr::Incomplete) => None,
+            Err(err) => {
+                // Drop the whole frame if its length is known, only its identifier otherwise
+                let id = self.frm[0];
+                self.consume(1 + arg_len(id).unwrap_or(0));
+                Some(Err((id, err)))
+            }
+        }
+    }
+
+    fn consume(&mut self, len: usize) {
+        let rem = self.frm.len() - len;
+        for idx in 0..rem {
+            self.frm[idx] = self.frm[idx + len];
+        }
+        self.frm.truncate(rem);
+    }
+}
@@ -1,4 +1,4 @@
-use heapless::Vec;
+use heapless::spsc::Queue;
 use heapless::consts::U32;
 use stm32f1::stm32f103::Interrupt;
 use stm32f1::stm32f103::interrupt;
@@ -6,6 +6,8 @@ use cortex_m::peripheral::NVIC;
 
 use crate::periph;
 
+pub mod cmd;
+
 #[derive(Copy, Clone)]
 pub enum SerSts {
     Sending,
@@ -15,15 +17,15 @@ pub enum SerSts {
 }
 
 struct SerRwBuf {
-    wr_buf: Vec<u8, U32>,
-    rd_buf: Vec<u8, U32>,
+    wr_buf: Queue<u8, U32>,
+    rd_buf: Queue<u8, U32>,
     status: SerSts,
     recv_nr: usize,
 }
 
 static mut BUF: SerRwBuf = SerRwBuf {
-    wr_buf : Vec(heapless::i::Vec::new()),
-    rd_buf : Vec(heapless::i::Vec::new()),
+    wr_buf : Queue(heapless::i::Queue::new()),
+    rd_buf : Queue(heapless::i::Queue::new()),
     status : SerSts::Idle,
     recv_nr: 0,
 };
@@ -90,9 +92,9 @@ pub fn init() {
 /// * Err(()): reception buffer couldn't be read, should not append at the moment
 pub fn read_data(dat: &mut[u8]) -> Result<usize, ()> {
     let mut recv = 0;
-    let buf = unsafe { &mut BUF };
+    let buf = unsafe { &mut *core::ptr::addr_of_mut!(BUF) };
     for d in dat.iter_mut() {
-        match buf.rd_buf.pop() {
+        match buf.rd_buf.dequeue() {
             Some(val) => {
                 *d = val;
                 recv += 1;
@@ -113,18 +115,18 @@ pub fn read_data(dat: &mut[u8]) -> Result<usize, ()> {
 /// 
 /// Either:
 /// * Ok(()): buffer ok to be sent
-/// * Err(()): sending buffer full
+/// * Err(()): sending buffer too full for the whole buffer, nothing queued
 pub fn send_data(dat: &[u8]) -> Result<(), ()> {
     let ser = periph!(USART2);
-    let mut buf = unsafe { &mut BUF };
+    let buf = unsafe { &mut *core::ptr::addr_of_mut!(BUF) };
+    // Frames are queued whole or not at all, the interrupt only frees room meanwhile
+    if buf.wr_buf.capacity() - buf.wr_buf.len() < dat.len() {
+        buf.status = SerSts::Error;
+        return Err(());
+    }
     for d in dat.iter() {
-        match buf.wr_buf.push(*d) {
-            Ok(()) => continue,
-            Err(_) => {
-                buf.status = SerSts::Error;
-                return Err(());
-            }
-        }
+        // Cannot fail, room was checked above
+        let _ = buf.wr_buf.enqueue(*d);
     }
     buf.status = SerSts::Sending;
     ser.cr1.modify(|_, w| w.txeie().enabled());
@@ -137,6 +139,7 @@ pub fn send_data(dat: &[u8]) -> Result<(), ()> {
 /// * Receiving: driver received something
 /// * Sending: driver is currently sending data
 /// * Error: error reported due to buffer overflow
+#[allow(dead_code)]
 pub fn get_state() -> SerSts {
     unsafe { BUF.status }
 }
@@ -144,10 +147,10 @@ pub fn get_state() -> SerSts {
 #[interrupt]
 fn USART2() {
     let usart = periph!(USART2);
-    let mut buffer = unsafe { &mut BUF };
+    let buffer = unsafe { &mut *core::ptr::addr_of_mut!(BUF) };
 
     if usart.sr.read().txe().bits() && usart.cr1.read().txeie().is_enabled() {
-        if let Some(data) = buffer.wr_buf.pop() {
+        if let Some(data) = buffer.wr_buf.dequeue() {
             usart.dr.write(|w| w.dr().bits(data as u16));
         } else {
             // Disable transmission interupt if nothing to send
@@ -158,7 +161,7 @@ fn USART2() {
 
     if usart.sr.read().rxne().bit_is_set() {
         let recv = usart.dr.read().bits();
-        match buffer.rd_buf.push(recv as u8) {
+        match buffer.rd_buf.enqueue(recv as u8) {
             Ok(()) => {
                 buffer.status = SerSts::Receiving;
                 buffer.recv_nr += 1;
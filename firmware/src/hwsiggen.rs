@@ -0,0 +1,507 @@
+use core::ops::Range;
+
+use crkcam::cmn::{AgRes, Direction, Edge, Polarity};
+use crkcam::sched::{OcCmd, Scheduler, CAM_NR_MAX};
+use crkcam::{cam::*, crk::*, profile::ProfilePlayer};
+use super::periph;
+
+/// Period of the 16 bits timer counters, in timer ticks
+const CNT_PERIOD: u64 = 0x1_0000;
+/// Timer ticks from reading a counter to programming its compare, events closer to 
+/// the counter being delayed not to be missed for a whole counter period
+const CNT_MARGIN: u64 = 72;
+
+use stm32f1::stm32f103::{interrupt, TIM2, TIM3};
+
+type TimRegs = stm32f1::stm32f103::tim2::RegisterBlock;
+
+/// Cam outputs on the channels 2 to 4 of TIM2, handled by its interrupt
+pub const TIM2_CAMS: Range<usize> = 0..3;
+/// Cam outputs on TIM3, counting along TIM2, handled by its interrupt
+pub const TIM3_CAMS: Range<usize> = 3..CAM_NR_MAX;
+
+/// Output of the generator
+#[derive(Copy, Clone)]
+enum Ch {
+    Crk,
+    Cam(usize),
+}
+
+/// Compare channel of a timer
+#[derive(Copy, Clone)]
+enum OcNr {
+    Oc1,
+    Oc2,
+    Oc3,
+    Oc4,
+}
+
+impl Ch {
+    /// Timer and compare channel generating the output
+    ///
+    /// * crank: TIM2 CH1, PA0
+    /// * cam 0: TIM2 CH2, PA1
+    /// * cam 1: TIM2 CH3, PB10 (partial remap)
+    /// * cam 2: TIM2 CH4, PB11 (partial remap)
+    /// * cam 3: TIM3 CH1, PA6
+    fn oc(self) -> (&'static TimRegs, OcNr) {
+        // Registers live for the whole program
+        let (tim2, tim3) = unsafe { (&*TIM2::ptr(), &*TIM3::ptr()) };
+        match self {
+            Ch::Crk => (tim2, OcNr::Oc1),
+            Ch::Cam(0) => (tim2, OcNr::Oc2),
+            Ch::Cam(1) => (tim2, OcNr::Oc3),
+            Ch::Cam(2) => (tim2, OcNr::Oc4),
+            Ch::Cam(_) => (tim3, OcNr::Oc1),
+        }
+    }
+}
+
+/// Output compare state of a channel
+///
+/// Events can be further apart than the 16 bits counter period. The counter is 
+/// extended by counting the compare matches happening before the event: the 
+/// output is frozen until the last period before the event, and only then 
+/// programmed to generate the event edge.
+///
+/// Event times are counted in timer ticks from the start, the counter being the 
+/// lower 16 bits of the time. Events the interrupt programs too late for the counter 
+/// to reach them are delayed, as they would only match a counter period later.
+#[derive(Copy, Clone)]
+struct OcState {
+    /// Time of the next event
+    t: u64,
+    /// Time of the previous event
+    prev: u64,
+    /// Number of compare matches to skip before the event
+    skip: u64,
+    /// Output edge to generate on the event, if any
+    edge: Option<Edge>,
+}
+
+impl OcState {
+    const fn new() -> OcState {
+        OcState {
+            t: 0,
+            prev: 0,
+            skip: 0,
+            edge: None,
+        }
+    }
+
+    /// Schedule the next event `cmd.dt` timer ticks after the previous one and program it, 
+    /// returning the timer ticks it's delayed by if the counter was already too close
+    fn schedule(&mut self, ch: Ch, cmd: OcCmd) -> u64 {
+        let OcCmd { dt, edge } = cmd;
+        // The counter is less than a period past the previous event, just handled
+        let prev = self.t;
+        let now = prev + cnt(ch).wrapping_sub(prev as u16) as u64;
+        self.prev = prev;
+        self.edge = edge;
+        self.program(ch, prev + dt, now)
+    }
+
+    /// Move the scheduled event `dt` timer ticks after the previous one, the counter being 
+    /// less than a period past `since`. Returns the timer ticks the event is delayed by, 
+    /// negative if it matched or is too close to be moved.
+    fn reschedule(&mut self, ch: Ch, dt: u64, since: u64) -> i64 {
+        let now = since + cnt(ch).wrapping_sub(since as u16) as u64;
+        let t = self.prev + dt;
+        if self.skip == 0 && (self.t <= now + CNT_MARGIN || is_matched(ch)) {
+            return self.t as i64 - t as i64;
+        }
+        // The match of a skipped period may be pending, skipped periods being counted again
+        clear_match(ch);
+        self.program(ch, t, now) as i64
+    }
+
+    /// Program the event at `t`, the counter being at `now`, returning the timer ticks 
+    /// it's delayed by if the counter is already too close
+    fn program(&mut self, ch: Ch, t: u64, now: u64) -> u64 {
+        self.t = t.max(now + CNT_MARGIN);
+        self.skip = (self.t - now - 1) / CNT_PERIOD;
+
+        let (tim, nr) = ch.oc();
+        let ccr = self.t as u16;
+        match nr {
+            OcNr::Oc1 => tim.ccr1.write(|w| w.ccr().bits(ccr)),
+            OcNr::Oc2 => tim.ccr2.write(|w| w.ccr().bits(ccr)),
+            OcNr::Oc3 => tim.ccr3.write(|w| w.ccr().bits(ccr)),
+            OcNr::Oc4 => tim.ccr4.write(|w| w.ccr().bits(ccr)),
+        }
+        set_oc_mode(ch, if self.skip > 0 { None } else { self.edge });
+        self.t - t
+    }
+
+    /// Handle a compare match, returning true if it's the scheduled event
+    fn on_match(&mut self, ch: Ch) -> bool {
+        if self.skip == 0 {
+            return true;
+        }
+        self.skip -= 1;
+        if self.skip == 0 {
+            // Last period before the event, program its edge
+            set_oc_mode(ch, self.edge);
+        }
+        false
+    }
+}
+
+/// Counter of the timer of an output
+fn cnt(ch: Ch) -> u16 {
+    let (tim, _) = ch.oc();
+    tim.cnt.read().bits() as u16
+}
+
+/// Check if the compare channel of an output matched
+fn is_matched(ch: Ch) -> bool {
+    let (tim, nr) = ch.oc();
+    let sr = tim.sr.read();
+    match nr {
+        OcNr::Oc1 => sr.cc1if().bit_is_set(),
+        OcNr::Oc2 => sr.cc2if().bit_is_set(),
+        OcNr::Oc3 => sr.cc3if().bit_is_set(),
+        OcNr::Oc4 => sr.cc4if().bit_is_set(),
+    }
+}
+
+/// Clear the compare match flag of an output
+fn clear_match(ch: Ch) {
+    let (tim, nr) = ch.oc();
+    match nr {
+        OcNr::Oc1 => tim.sr.modify(|_, w| w.cc1if().clear()),
+        OcNr::Oc2 => tim.sr.modify(|_, w| w.cc2if().clear()),
+        OcNr::Oc3 => tim.sr.modify(|_, w| w.cc3if().clear()),
+        OcNr::Oc4 => tim.sr.modify(|_, w| w.cc4if().clear()),
+    }
+}
+
+/// Check if the compare channel of an output matched and clear its flag
+fn take_match(ch: Ch) -> bool {
+    let matched = is_matched(ch);
+    if matched {
+        clear_match(ch);
+    }
+    matched
+}
+
+pub struct Timer {
+    sched: Scheduler,
+    cam_oc: [OcState; CAM_NR_MAX],
+    crk_oc: OcState,
+}
+
+impl Timer {
+    /// `freq` is the APB1 clock frequency, Hz. TIM2 is clocked at twice this 
+    /// frequency as APB1 is prescaled. Generators shall have the resolution `res`.
+    pub const fn new(freq: u32, res: AgRes) -> Timer {
+        Timer {
+            sched: Scheduler::new(2 * freq as u64, res),
+            cam_oc: [OcState::new(); CAM_NR_MAX],
+            crk_oc: OcState::new(),
+        }
+    }
+
+    /// Check if generators, direction or crank mode changes are still waiting to be applied
+    pub fn is_pending(&self) -> bool {
+        self.sched.is_pending()
+    }
+
+    /// Number of timer ticks per second, the time unit of profile players
+    pub fn tick_freq(&self) -> u64 {
+        self.sched.tick_freq()
+    }
+
+    /// Generation speed, 0.01 rpm. It's the requested speed, limited to the range 
+    /// supported, period dithering making it exact on average.
+    pub fn speed_crpm(&self) -> u32 {
+        self.sched.speed_crpm()
+    }
+
+    /// One timer tick relative to the period of one degree at the generation speed, ppm, 
+    /// bounding the deviation of a degree from its average
+    pub fn deg_tick_ppm(&self) -> u32 {
+        self.sched.deg_tick_ppm()
+    }
+
+    /// Program the output polarity of all channels if it changed
+    fn apply_polarity(&mut self) {
+        if !self.sched.take_pol_changed() {
+            return;
+        }
+        let (crk, cams) = self.sched.polarity();
+        let inverted = |idx: usize| cams[idx] == Polarity::Inverted;
+
+        // Active low if inverted
+        periph!(TIM2).ccer.modify(|_, w| {
+            w.cc1p().bit(crk == Polarity::Inverted)
+            .cc2p().bit(inverted(0))
+            .cc3p().bit(inverted(1))
+            .cc4p().bit(inverted(2))
+        });
+        periph!(TIM3).ccer.modify(|_, w| w.cc1p().bit(inverted(3)));
+    }
+}
+
+fn init_timer(tim: &TimRegs) {
+    tim.cr1.modify(|_, w| {
+        w.ckd().div1()
+            .arpe().disabled()
+            .cms().edge_aligned()
+            .dir().up()
+            .opm().disabled()
+            .urs().any_event()
+            .udis().enabled()
+    });
+
+    tim.ccmr1_output_mut().modify(|_, w| {
+        w.cc1s().output()
+        .cc2s().output()
+        .oc1pe().disabled()
+        .oc2pe().disabled()
+    });
+    tim.ccmr2_output_mut().modify(|_, w| {
+        w.cc3s().output()
+        .cc4s().output()
+        .oc3pe().disabled()
+        .oc4pe().disabled()
+    });
+
+    tim.arr.write(|w| w.arr().bits((CNT_PERIOD - 1) as u16));
+    // Timer counts at its clock frequency, event times are scaled to the speed
+    tim.psc.write(|w| w.psc().bits(0));
+    tim.egr.write(|w| w.ug().set_bit());
+}
+
+/// Configure TIM2 for the crank and the first cams, and TIM3 for the last cam, counting 
+/// only while TIM2 is enabled for both counters to stay equal
+fn init_timers() {
+    let tim2 = periph!(TIM2);
+    let tim3 = periph!(TIM3);
+    init_timer(tim2);
+    init_timer(tim3);
+
+    tim2.ccer.modify(|_, w| {
+        w.cc1e().set_bit() // output capture enabled
+        .cc2e().set_bit()
+        .cc3e().set_bit()
+        .cc4e().set_bit()
+        .cc1p().clear_bit() // active high
+        .cc2p().clear_bit()
+        .cc3p().clear_bit()
+        .cc4p().clear_bit()
+    });
+    tim3.ccer.modify(|_, w| w.cc1e().set_bit().cc1p().clear_bit());
+
+    // TIM2 counter enable is the trigger output, gating TIM3 (ITR1)
+    tim2.cr2.modify(|_, w| w.mms().enable());
+    tim3.smcr.modify(|_, w| w.ts().itr1().sms().gated_mode());
+
+    tim2.dier.modify(|_, w| {
+        w.cc1ie().enabled() // enable interrupts on output compare channels
+        .cc2ie().enabled()
+        .cc3ie().enabled()
+        .cc4ie().enabled()
+    });
+    tim3.dier.modify(|_, w| w.cc1ie().enabled());
+
+    let dbg = periph!(DBGMCU);
+    dbg.cr.modify(|_, w| w.dbg_tim2_stop().set_bit().dbg_tim3_stop().set_bit());
+}
+
+/// Program an output to follow `edge` on the next compare match, or to be left 
+/// unchanged if `edge` is None
+fn set_oc_mode(ch: Ch, edge: Option<Edge>) {
+    let (tim, nr) = ch.oc();
+    match (nr, edge) {
+        (OcNr::Oc1, Some(Edge::Rising)) => tim.ccmr1_output_mut().modify(|_, w| w.oc1m().active_on_match()),
+        (OcNr::Oc1, Some(Edge::Falling)) => tim.ccmr1_output_mut().modify(|_, w| w.oc1m().inactive_on_match()),
+        (OcNr::Oc1, None) => tim.ccmr1_output_mut().modify(|_, w| w.oc1m().frozen()),
+        (OcNr::Oc2, Some(Edge::Rising)) => tim.ccmr1_output_mut().modify(|_, w| w.oc2m().active_on_match()),
+        (OcNr::Oc2, Some(Edge::Falling)) => tim.ccmr1_output_mut().modify(|_, w| w.oc2m().inactive_on_match()),
+        (OcNr::Oc2, None) => tim.ccmr1_output_mut().modify(|_, w| w.oc2m().frozen()),
+        (OcNr::Oc3, Some(Edge::Rising)) => tim.ccmr2_output_mut().modify(|_, w| w.oc3m().active_on_match()),
+        (OcNr::Oc3, Some(Edge::Falling)) => tim.ccmr2_output_mut().modify(|_, w| w.oc3m().inactive_on_match()),
+        (OcNr::Oc3, None) => tim.ccmr2_output_mut().modify(|_, w| w.oc3m().frozen()),
+        (OcNr::Oc4, Some(Edge::Rising)) => tim.ccmr2_output_mut().modify(|_, w| w.oc4m().active_on_match()),
+        (OcNr::Oc4, Some(Edge::Falling)) => tim.ccmr2_output_mut().modify(|_, w| w.oc4m().inactive_on_match()),
+        (OcNr::Oc4, None) => tim.ccmr2_output_mut().modify(|_, w| w.oc4m().frozen()),
+    }
+}
+
+fn init_gpio() {
+    //A0 -> TIM2_CH1, A1 -> TIM2_CH2 and A6 -> TIM3_CH1, B10 -> TIM2_CH3 and 
+    //B11 -> TIM2_CH4 with the partial remap 2, A2 and A3 being used by USART2
+    let rcc = periph!(RCC);
+    let afio = periph!(AFIO);
+    let pa = periph!(GPIOA);
+    let pb = periph!(GPIOB);
+
+    rcc.apb2enr.modify(|_, w| w.iopaen().enabled().iopben().enabled().afioen().enabled());
+    afio.mapr.modify(|_, w| unsafe { w.tim2_remap().bits(0b10) });
+
+    pa.crl.modify(|_, w| {
+        w.cnf0()
+            .alt_push_pull()
+            .mode0()
+            .output()
+            .cnf1()
+            .alt_push_pull()
+            .mode1()
+            .output()
+            .cnf6()
+            .alt_push_pull()
+            .mode6()
+            .output()
+    });
+    pb.crh.modify(|_, w| {
+        w.cnf10()
+            .alt_push_pull()
+            .mode10()
+            .output()
+            .cnf11()
+            .alt_push_pull()
+            .mode11()
+            .output()
+    });
+}
+
+impl crkcam::siggen::CrkCamSigGen for Timer {
+    fn initialize(&mut self, cam: CamSigGen, crk: CrkSigGen) {
+        let rcc = periph!(RCC);
+
+        rcc.apb1enr.modify(|_, w| w.tim2en().enabled().tim3en().enabled());
+
+        init_timers();
+        init_gpio();
+
+        self.sched.initialize(cam, crk);
+        self.apply_polarity();
+
+        //Init interrupts
+        unsafe {
+            let mut nvic = cortex_m::Peripherals::steal().NVIC;
+            // Both at the same priority, not to preempt each other
+            nvic.set_priority(interrupt::TIM2, 2);
+            nvic.set_priority(interrupt::TIM3, 2);
+            cortex_m::peripheral::NVIC::unmask(interrupt::TIM2);
+            cortex_m::peripheral::NVIC::unmask(interrupt::TIM3);
+        }
+    }
+
+    fn set_sig_gen(&mut self, cam: CamSigGen, crk: CrkSigGen) {
+        // Generators are swapped from the crank interrupt, on the reference
+        self.sched.set_sig_gen(cam, crk);
+    }
+
+    fn initialize_cam(&mut self, idx: usize, cam: Option<CamSigGen>) {
+        self.sched.initialize_cam(idx, cam);
+        self.apply_polarity();
+    }
+
+    fn set_cam_sig_gen(&mut self, idx: usize, cam: Option<CamSigGen>) {
+        // Generators are swapped from the crank interrupt, on the reference
+        self.sched.set_cam_sig_gen(idx, cam);
+    }
+
+    fn set_direction(&mut self, dir: Direction) {
+        // Direction is changed from the crank interrupt, on the reference
+        self.sched.set_direction(dir);
+    }
+
+    fn set_crk_mode(&mut self, mode: CrkMode) {
+        // Mode is changed from the crank interrupt, on the reference
+        self.sched.set_crk_mode(mode);
+    }
+
+    fn set_polarity(&mut self, crk: Polarity, idx: usize, cam: Polarity) {
+        self.sched.set_polarity(crk, idx, cam);
+        self.apply_polarity();
+    }
+
+    fn set_speed_crpm(&mut self, spd: u32) {
+        self.sched.set_speed_crpm(spd);
+    }
+
+    fn set_speed_ramp(&mut self, target: u32, rate: u32) {
+        self.sched.set_speed_ramp(target, rate);
+    }
+
+    fn set_profile(&mut self, player: Option<ProfilePlayer>) {
+        self.sched.set_profile(player);
+    }
+
+    fn pause_profile(&mut self, paused: bool) {
+        self.sched.pause_profile(paused);
+    }
+
+    fn set_cam_offset(&mut self, idx: usize, offset: i32) {
+        self.sched.set_cam_offset(idx, offset);
+    }
+
+    fn set_cam_vvt(&mut self, idx: usize, target: i32, rate: u32, osc_amp: u32, osc_period_ms: u32) {
+        self.sched.set_cam_vvt(idx, target, rate, osc_amp, osc_period_ms);
+    }
+
+    fn set_next_crk_ev(&mut self) {
+        // Check if this is really an event on the crk channel and clear it
+        // otherwise, return without doing anything
+        if take_match(Ch::Crk) {
+            if !self.crk_oc.on_match(Ch::Crk) {
+                return;
+            }
+
+            let cmd = self.sched.next_crk();
+            let late = self.crk_oc.schedule(Ch::Crk, cmd);
+            self.sched.delay_crk(late);
+            // Speed changes move the scheduled cam compares
+            let since = self.crk_oc.prev;
+            for idx in 0..CAM_NR_MAX {
+                if let Some(dt) = self.sched.take_cam_moved(idx) {
+                    let late = self.cam_oc[idx].reschedule(Ch::Cam(idx), dt, since);
+                    self.sched.delay_cam(idx, late);
+                }
+            }
+            // Generators may have been swapped on the reference
+            self.apply_polarity();
+        }
+    }
+
+    fn set_next_cam_ev(&mut self, idx: usize) {
+        // Check if this is really an event on the cam channel and clear it
+        // otherwise, return without doing anything
+        let ch = Ch::Cam(idx);
+        if take_match(ch) {
+            if !self.cam_oc[idx].on_match(ch) {
+                return;
+            }
+
+            let cmd = self.sched.next_cam(idx);
+            let late = self.cam_oc[idx].schedule(ch, cmd);
+            self.sched.delay_cam(idx, late as i64);
+        }
+    }
+
+    fn start(&mut self) {
+        let tim2 = periph!(TIM2);
+        let tim3 = periph!(TIM3);
+        tim2.cr1.modify(|_, w| w.cen().disabled());
+        tim2.cnt.write(|w| unsafe{w.bits(0)});
+        tim3.cnt.write(|w| unsafe{w.bits(0)});
+
+        // Counters are stopped, first events are programmed in time
+        let (crk, cams) = self.sched.start();
+        self.crk_oc = OcState::new();
+        self.crk_oc.schedule(Ch::Crk, crk);
+        for (idx, cam) in cams.iter().enumerate() {
+            self.cam_oc[idx] = OcState::new();
+            self.cam_oc[idx].schedule(Ch::Cam(idx), *cam);
+        }
+
+        tim2.sr.modify(|_, w| w.cc1if().clear().cc2if().clear().cc3if().clear().cc4if().clear());
+        tim3.sr.modify(|_, w| w.cc1if().clear());
+        // TIM3 counts along TIM2, from its enable
+        tim3.cr1.modify(|_, w| w.cen().enabled());
+        tim2.cr1.modify(|_, w| w.cen().enabled());
+    }
+}
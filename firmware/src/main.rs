@@ -0,0 +1,219 @@
+#![no_main]
+#![no_std]
+
+extern crate panic_halt;
+
+mod hwsiggen;
+mod system;
+mod periph;
+mod com;
+
+use cortex_m_rt::entry;
+use heapless::consts::{U4, U8};
+use heapless::Vec;
+use stm32f1::stm32f103::interrupt;
+
+use com::cmd::{self, Cmd, CmdErr, CmdReader, ProfileAction};
+use crkcam::{
+    cam::*,
+    cam_cfg::*,
+    cmn::AgRes,
+    crk::*,
+    crk_cfg::*,
+    pattern,
+    pattern_cfg::PATTERNS,
+    profile::ProfilePlayer,
+    profile_cfg::PROFILES,
+};
+use crkcam::siggen::CrkCamSigGen;
+use hwsiggen::Timer;
+
+/// Angular resolution of the generation
+const AG_RES: AgRes = AgRes::DEG_0_01;
+
+static mut GEN_TIM: Timer = Timer::new(36_000_000, AG_RES);
+
+#[interrupt]
+fn TIM2() {
+    let tim = unsafe { &mut *core::ptr::addr_of_mut!(GEN_TIM) };
+    tim.set_next_crk_ev();
+    for idx in hwsiggen::TIM2_CAMS {
+        tim.set_next_cam_ev(idx);
+    }
+}
+
+#[interrupt]
+fn TIM3() {
+    let tim = unsafe { &mut *core::ptr::addr_of_mut!(GEN_TIM) };
+    for idx in hwsiggen::TIM3_CAMS {
+        tim.set_next_cam_ev(idx);
+    }
+}
+
+/// Create the crank and cam signal generators for the given configuration ids
+fn create_sig_gen(crk_cfg_id: usize, cam_cfg_id: usize) -> Result<(CamSigGen, CrkSigGen), CmdErr> {
+    let crk_cfg = CRK_CONFIGS.get(crk_cfg_id).ok_or(CmdErr::InvalidArg)?;
+    let cam_cfg = CAM_CONFIGS.get(cam_cfg_id).ok_or(CmdErr::InvalidArg)?;
+    let cam_gen = CamSigGen::new(cam_cfg, AG_RES).map_err(|_| CmdErr::InvalidArg)?;
+    let crk_gen = CrkSigGen::new(crk_cfg, AG_RES).map_err(|_| CmdErr::InvalidArg)?;
+    Ok((cam_gen, crk_gen))
+}
+
+/// Responses of commands applied on the crank reference, sent once they are active
+type DeferredRsp = Vec<Vec<u8, U8>, U4>;
+
+/// Queue the response of a command applied on the crank reference
+fn defer_rsp(deferred: &mut DeferredRsp, rsp: &[u8]) -> Result<(), CmdErr> {
+    // Cannot fail, responses are shorter than the buffer
+    let rsp = Vec::from_slice(rsp).unwrap();
+    deferred.push(rsp).map_err(|_| CmdErr::Busy)
+}
+
+/// Execute a command received through the serial link and send back its response
+fn process_cmd(tim: &mut Timer, cmd: Cmd, deferred: &mut DeferredRsp) -> Result<(), CmdErr> {
+    match cmd {
+        Cmd::SetCfg { crk_id, cam_id } => {
+            let (cam_gen, crk_gen) = create_sig_gen(crk_id as usize, cam_id as usize)?;
+            defer_rsp(deferred, &[cmd.id(), crk_id, cam_id])?;
+            cortex_m::interrupt::free(|_| tim.set_sig_gen(cam_gen, crk_gen));
+        }
+        Cmd::SetPol { cam, crk_pol, cam_pol } => {
+            cortex_m::interrupt::free(|_| tim.set_polarity(crk_pol, cam as usize, cam_pol));
+            let _ = cmd::send_pol(cam, crk_pol, cam_pol);
+        }
+        Cmd::SetDir { dir } => {
+            defer_rsp(deferred, &[cmd.id(), cmd::dir_to_u8(dir)])?;
+            cortex_m::interrupt::free(|_| tim.set_direction(dir));
+        }
+        Cmd::SetCrkMode { mode } => {
+            let arg = cmd::crk_mode_to_bytes(mode);
+            defer_rsp(deferred, &[cmd.id(), arg[0], arg[1], arg[2], arg[3], arg[4]])?;
+            cortex_m::interrupt::free(|_| tim.set_crk_mode(mode));
+        }
+        Cmd::SetSpeed { crpm } => {
+            let (set, tick_ppm) = cortex_m::interrupt::free(|_| {
+                tim.set_speed_crpm(crpm);
+                (tim.speed_crpm(), tim.deg_tick_ppm())
+            });
+            let _ = cmd::send_speed(crpm, set, tick_ppm);
+        }
+        Cmd::SetRamp { crpm, rate } => {
+            cortex_m::interrupt::free(|_| tim.set_speed_ramp(crpm, rate as u32));
+            let _ = cmd::send_ramp(crpm, rate);
+        }
+        Cmd::Profile { action } => {
+            let rsp = match action {
+                ProfileAction::Stop => {
+                    cortex_m::interrupt::free(|_| tim.set_profile(None));
+                    [cmd.id(), 0, 0, 0]
+                }
+                ProfileAction::Play { id, looped } => {
+                    let prof = PROFILES.get(id as usize).ok_or(CmdErr::InvalidArg)?;
+                    let player = ProfilePlayer::new(prof, looped, tim.tick_freq());
+                    cortex_m::interrupt::free(|_| tim.set_profile(Some(player)));
+                    [cmd.id(), 1, id, looped as u8]
+                }
+                ProfileAction::Pause => {
+                    cortex_m::interrupt::free(|_| tim.pause_profile(true));
+                    [cmd.id(), 2, 0, 0]
+                }
+                ProfileAction::Resume => {
+                    cortex_m::interrupt::free(|_| tim.pause_profile(false));
+                    [cmd.id(), 3, 0, 0]
+                }
+            };
+            let _ = com::send_data(&rsp);
+        }
+        Cmd::ListPattern { idx } => {
+            let pat = PATTERNS.get(idx as usize).ok_or(CmdErr::InvalidArg)?;
+            let _ = cmd::send_pattern(idx, PATTERNS.len() as u8, pat);
+        }
+        Cmd::SelectPattern { name } => {
+            let idx = pattern::find(&name).ok_or(CmdErr::InvalidArg)?;
+            let pat = &PATTERNS[idx];
+            let (cam_gen, crk_gen) = create_sig_gen(pat.crk_id as usize, pat.cam_id as usize)?;
+            defer_rsp(deferred, &[cmd.id(), idx as u8, pat.crk_id, pat.cam_id])?;
+            cortex_m::interrupt::free(|_| tim.set_sig_gen(cam_gen, crk_gen));
+        }
+        Cmd::SetCamOffset { cam, offset } => {
+            cortex_m::interrupt::free(|_| tim.set_cam_offset(cam as usize, offset as i32));
+            let off = offset.to_be_bytes();
+            let _ = com::send_data(&[cmd.id(), cam, off[0], off[1]]);
+        }
+        Cmd::SetCamVvt { cam, target, rate, osc_amp, osc_period_ms } => {
+            cortex_m::interrupt::free(|_| {
+                tim.set_cam_vvt(cam as usize, target as i32, rate as u32, osc_amp as u32, osc_period_ms as u32)
+            });
+            let _ = cmd::send_cam_vvt(cam, target, rate, osc_amp, osc_period_ms);
+        }
+        Cmd::SelectCam { cam, cam_id } => {
+            let cam_gen = match cam_id {
+                cmd::CAM_ID_NONE => None,
+                id => {
+                    let cam_cfg = CAM_CONFIGS.get(id as usize).ok_or(CmdErr::InvalidArg)?;
+                    Some(CamSigGen::new(cam_cfg, AG_RES).map_err(|_| CmdErr::InvalidArg)?)
+                }
+            };
+            defer_rsp(deferred, &[cmd.id(), cam, cam_id])?;
+            cortex_m::interrupt::free(|_| tim.set_cam_sig_gen(cam as usize, cam_gen));
+        }
+    }
+    Ok(())
+}
+
+/// Send the deferred responses once the timer applied all pending commands, in order.
+/// Responses not fitting in the sending buffer are kept and retried on the next call.
+fn send_deferred(tim: &Timer, deferred: &mut DeferredRsp) {
+    if deferred.is_empty() || cortex_m::interrupt::free(|_| tim.is_pending()) {
+        return;
+    }
+    let sent = deferred.iter().take_while(|rsp| com::send_data(rsp).is_ok()).count();
+    if sent < deferred.len() {
+        // Cannot fail, fewer responses than before
+        *deferred = Vec::from_slice(&deferred[sent..]).unwrap();
+    } else {
+        deferred.clear();
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    system::init_clks();
+    com::init();
+
+    let speed = 1000;
+    let cam_cfg_id = 0;
+    let crk_cfg_id = 0;
+
+    let tim = unsafe { &mut *core::ptr::addr_of_mut!(GEN_TIM) };
+    let (cam_gen, crk_gen) = match create_sig_gen(crk_cfg_id, cam_cfg_id) {
+        Ok(sig_gen) => sig_gen,
+        Err(_) => {
+            com::send_data(&[0xFF, 0xFF, 0xFF]).unwrap();
+            panic!("Cannot create cam config.");
+        },
+    };
+    tim.initialize(cam_gen, crk_gen);
+    tim.set_speed_rpm(speed);
+    tim.start();
+
+    let mut reader = CmdReader::new();
+    let mut deferred = DeferredRsp::new();
+    loop {
+        match reader.poll() {
+            Some(Ok(cmd)) => {
+                if let Err(err) = process_cmd(tim, cmd, &mut deferred) {
+                    let _ = cmd::send_err(cmd.id(), err);
+                }
+            },
+            Some(Err((id, err))) => {
+                let _ = cmd::send_err(id, err);
+            },
+            None => (),
+        }
+        send_deferred(tim, &mut deferred);
+        for _ in 0..10000 {
+            cortex_m::asm::nop();
+        }
+    }
+}
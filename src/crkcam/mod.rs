@@ -1,6 +0,0 @@
-pub mod cam;
-pub mod cam_cfg;
-pub mod cmn;
-pub mod crk;
-pub mod crk_cfg;
-pub mod siggen;
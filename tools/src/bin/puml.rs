@@ -0,0 +1,78 @@
+//! Render a crank and/or cam configuration as a PlantUML timing diagram, in
+//! degrees over the 720° cycle.
+//!
+//! Usage: `puml <crk cfg|-> <cam cfg|-> [output file]`, configurations being
+//! indexes in `CRK_CONFIGS` and `CAM_CONFIGS`, `-` leaving the signal out of the
+//! diagram. The diagram is written to the standard output if no file is given.
+use crkcam::cam::CamSigGen;
+use crkcam::cam_cfg::CAM_CONFIGS;
+use crkcam::cmn::AgRes;
+use crkcam::crk::CrkSigGen;
+use crkcam::crk_cfg::CRK_CONFIGS;
+use crkcam::puml::write_puml;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process;
+
+const USAGE: &str = "usage: puml <crk cfg|-> <cam cfg|-> [output file]";
+
+/// Index of the configuration given by an argument, None if left out
+fn parse_cfg(args: &[String], idx: usize, name: &str, nr: usize) -> Result<Option<usize>, String> {
+    match args.get(idx).map(String::as_str) {
+        None => Err(USAGE.to_string()),
+        Some("-") => Ok(None),
+        Some(arg) => match arg.parse() {
+            Ok(id) if id < nr => Ok(Some(id)),
+            _ => Err(format!("no {} configuration {}", name, arg)),
+        },
+    }
+}
+
+/// Diagram of the configurations
+fn render<W: Write>(w: &mut W, crk_id: Option<usize>, cam_id: Option<usize>) -> Result<(), String> {
+    let crk = match crk_id {
+        Some(id) => Some(CrkSigGen::new(&CRK_CONFIGS[id], AgRes::DEG_0_01).map_err(|e| format!("invalid crank configuration {}: {:?}", id, e))?),
+        None => None,
+    };
+    let cam = match cam_id {
+        Some(id) => Some(CamSigGen::new(&CAM_CONFIGS[id], AgRes::DEG_0_01).map_err(|e| format!("invalid cam configuration {}: {:?}", id, e))?),
+        None => None,
+    };
+    write_puml(w, crk, cam).map_err(|e| e.to_string())
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let crk_id = parse_cfg(args, 1, "crank", CRK_CONFIGS.len())?;
+    let cam_id = parse_cfg(args, 2, "cam", CAM_CONFIGS.len())?;
+
+    let mut out: Box<dyn Write> = match args.get(3) {
+        Some(path) => Box::new(File::create(path).map_err(|e| format!("{}: {}", path, e))?),
+        None => Box::new(io::stdout()),
+    };
+    let mut out = BufWriter::new(&mut out);
+    render(&mut out, crk_id, cam_id)?;
+    out.flush().map_err(|e| e.to_string())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if let Err(msg) = run(&args) {
+        eprintln!("{}", msg);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timing_diagram_is_up_to_date() {
+        // Regenerate with `cargo run --bin puml -- - 0 crk_cam_timings.puml`
+        let mut out = Vec::new();
+        render(&mut out, None, Some(0)).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), include_str!("../../../crk_cam_timings.puml"));
+    }
+}
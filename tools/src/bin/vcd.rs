@@ -0,0 +1,70 @@
+//! Simulate the generation of a crank and cam configuration at a given speed
+//! and dump the signals as a VCD.
+//!
+//! Usage: `vcd <crk cfg> <cam cfg>[,<cam cfg>...] <rpm> <duration ms> [output file]`,
+//! configurations being indexes in `CRK_CONFIGS` and `CAM_CONFIGS`, cam configurations
+//! being generated on the cam outputs in order. The dump is written to the standard
+//! output if no file is given.
+use crkcam::cam::CamSigGen;
+use crkcam::cam_cfg::CAM_CONFIGS;
+use crkcam::cmn::{AgRes, CRPM_PER_RPM};
+use crkcam::crk::CrkSigGen;
+use crkcam::crk_cfg::CRK_CONFIGS;
+use crkcam::sched::CAM_NR_MAX;
+use crkcam::sim::{simulate, TIM_CLK};
+use crkcam::vcd::write_vcd;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process;
+
+const USAGE: &str = "usage: vcd <crk cfg> <cam cfg>[,<cam cfg>...] <rpm> <duration ms> [output file]";
+
+fn parse_arg(args: &[String], idx: usize, name: &str) -> Result<u32, String> {
+    let arg = args.get(idx).ok_or_else(|| USAGE.to_string())?;
+    arg.parse().map_err(|_| format!("invalid {}: {}", name, arg))
+}
+
+/// Cam generators of a comma separated list of cam configurations
+fn parse_cams(args: &[String], idx: usize) -> Result<Vec<CamSigGen>, String> {
+    let arg = args.get(idx).ok_or_else(|| USAGE.to_string())?;
+    let cams = arg.split(',').map(|id| {
+        let cam_id: usize = id.parse().map_err(|_| format!("invalid cam configuration: {}", id))?;
+        let cam_cfg = CAM_CONFIGS.get(cam_id).ok_or(format!("no cam configuration {}", cam_id))?;
+        CamSigGen::new(cam_cfg, AgRes::DEG_0_01).map_err(|e| format!("invalid cam configuration {}: {:?}", cam_id, e))
+    }).collect::<Result<Vec<_>, _>>()?;
+    if cams.len() > CAM_NR_MAX {
+        return Err(format!("at most {} cam configurations", CAM_NR_MAX));
+    }
+    Ok(cams)
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let crk_id = parse_arg(args, 1, "crank configuration")? as usize;
+    let cams = parse_cams(args, 2)?;
+    let rpm = parse_arg(args, 3, "speed")?;
+    let dur_ms = parse_arg(args, 4, "duration")?;
+
+    let crk_cfg = CRK_CONFIGS.get(crk_id).ok_or(format!("no crank configuration {}", crk_id))?;
+    let crk = CrkSigGen::new(crk_cfg, AgRes::DEG_0_01).map_err(|e| format!("invalid crank configuration {}: {:?}", crk_id, e))?;
+
+    let trans = simulate(cams, crk, rpm * CRPM_PER_RPM, dur_ms as u64 * 1_000);
+
+    let mut out: Box<dyn Write> = match args.get(5) {
+        Some(path) => Box::new(File::create(path).map_err(|e| format!("{}: {}", path, e))?),
+        None => Box::new(io::stdout()),
+    };
+    let mut out = BufWriter::new(&mut out);
+    write_vcd(&mut out, &trans, TIM_CLK)
+        .and_then(|_| out.flush())
+        .map_err(|e| e.to_string())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if let Err(msg) = run(&args) {
+        eprintln!("{}", msg);
+        process::exit(1);
+    }
+}